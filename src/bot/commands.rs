@@ -7,14 +7,16 @@ use std::string::ToString;
 use std::thread::sleep;
 use std::time::SystemTime;
 
-use futures::{Stream, StreamExt};
+use futures::Stream;
 use log::{error, info, warn};
 use poise::{CreateReply, serenity_prelude as serenity};
-use poise::serenity_prelude::CreateAttachment;
+use poise::serenity_prelude::{AutocompleteChoice, CreateAttachment, CreateEmbed};
 use serenity::futures;
 
-use crate::{download_watcher, xml};
-use crate::bot::{Context, Error};
+use crate::{download_watcher, matcher, parser, xml};
+use crate::bot::{Context, Data, Error};
+use crate::download_watcher::pattern_mapping;
+use crate::xml::{MappingType, Numbering, PatternMapping};
 
 /// Show this help menu
 #[poise::command(prefix_command, track_edits, slash_command)]
@@ -103,8 +105,55 @@ pub async fn reload(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Shows the files in the download folder that are not moved yet
+#[poise::command(slash_command, prefix_command)]
+pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let pending_files = match &ctx.data().shared_thread_infos {
+        None => {
+            ctx.say("Download Watcher not started").await?;
+            return Ok(());
+        }
+        Some(shared_data) => shared_data.lock().unwrap().pending_files.clone(),
+    };
+    // embed descriptions can only be 4096 characters long
+    let mut output = String::new();
+    for (index, file) in pending_files.iter().enumerate() {
+        let line = format!("`{}`: {}\n", file.name.replace('`', "\\`"), file.reason);
+        if output.len() + line.len() > 4000 {
+            output.push_str(format!("... and {} more", pending_files.len() - index).as_str());
+            break;
+        }
+        output.push_str(line.as_str());
+    }
+    if output.is_empty() {
+        output = "No pending files".to_string();
+    }
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::default()
+                .title("Pending files")
+                .description(output),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
 /// Parent Map Command
-#[poise::command(slash_command, subcommands("all", "new"))]
+#[poise::command(
+    slash_command,
+    subcommands(
+        "all",
+        "list",
+        "show",
+        "new",
+        "pattern",
+        "edit",
+        "numbering",
+        "rename",
+        "delete"
+    )
+)]
 pub async fn map(_: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
@@ -112,15 +161,7 @@ pub async fn map(_: Context<'_>) -> Result<(), Error> {
 /// Will show all Mappings
 #[poise::command(slash_command, prefix_command)]
 pub async fn all(ctx: Context<'_>) -> Result<(), Error> {
-    let mut mappings: HashMap<String, Vec<&str>> = HashMap::new();
-    let reverse_mapping = xml::get_mappings();
-    reverse_mapping.iter().for_each(|(to_replace, og)| {
-        if let Some(list) = mappings.get_mut(og) {
-            list.push(to_replace);
-        } else {
-            mappings.insert(og.to_string(), vec![to_replace]);
-        }
-    });
+    let mappings = get_mappings_by_og();
     let output = mappings
         .iter()
         .map(|(og, list)| {
@@ -144,7 +185,103 @@ pub async fn all(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
-#[allow(dead_code)]
+/// Will show all Mappings as pages of an embed
+#[poise::command(slash_command, prefix_command)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    const MAPPINGS_PER_PAGE: usize = 15;
+    let mappings = get_mappings_by_og();
+    if mappings.is_empty() {
+        ctx.say("No mappings").await?;
+        return Ok(());
+    }
+    let mut ogs: Vec<&String> = mappings.keys().collect();
+    ogs.sort();
+    let lines: Vec<String> = ogs
+        .iter()
+        .map(|og| format!("**{}**: `{}`", og, mappings[*og].join("`, `")))
+        .collect();
+    let pages: Vec<String> = lines
+        .chunks(MAPPINGS_PER_PAGE)
+        .enumerate()
+        .map(|(index, chunk)| {
+            format!(
+                "{}\n\nPage {}/{}",
+                chunk.join("\n"),
+                index + 1,
+                lines.len().div_ceil(MAPPINGS_PER_PAGE)
+            )
+        })
+        .collect();
+    let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+    poise::builtins::paginate(ctx, &pages).await?;
+    Ok(())
+}
+
+/// Will show the Mappings of one series
+#[poise::command(slash_command, prefix_command)]
+pub async fn show(
+    ctx: Context<'_>,
+    #[description = "series name on server"]
+    #[autocomplete = "autocomplete_og"]
+    og: String,
+) -> Result<(), Error> {
+    let og = og.to_lowercase();
+    match get_mappings_by_og().get(&og) {
+        None => ctx.say(format!("No mappings for `{}`", og)).await?,
+        Some(alts) => {
+            ctx.send(
+                CreateReply::default().embed(
+                    CreateEmbed::default()
+                        .title(format!("Mappings of {}", og))
+                        .description(format!("`{}`", alts.join("`\n`"))),
+                ),
+            )
+            .await?
+        }
+    };
+    Ok(())
+}
+
+/// Will group the alternative names and patterns of all Mappings by their series, sorted by name
+fn get_mappings_by_og() -> HashMap<String, Vec<String>> {
+    let mut mappings: HashMap<String, Vec<String>> = HashMap::new();
+    let numberings = xml::get_numberings();
+    xml::get_mappings().into_iter().for_each(|(alt, og)| {
+        let alt = match numberings.get(&alt) {
+            None => alt,
+            Some(numbering) => format!("{} ({})", alt, numbering),
+        };
+        mappings.entry(og).or_default().push(alt);
+    });
+    xml::get_pattern_mappings().into_iter().for_each(|mapping| {
+        let numbering = if mapping.numbering == Numbering::default() {
+            String::new()
+        } else {
+            format!(", {}", mapping.numbering)
+        };
+        mappings.entry(mapping.og.to_lowercase()).or_default().push(format!(
+            "{} ({}{})",
+            mapping.pattern, mapping.mapping_type, numbering
+        ));
+    });
+    mappings.values_mut().for_each(|alts| alts.sort());
+    mappings
+}
+
+/// autocomplete the alternative names of the existing Mappings
+async fn autocomplete_mapping<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+    let mut alts: Vec<String> = xml::get_mappings().into_keys().collect();
+    alts.extend(
+        xml::get_pattern_mappings()
+            .into_iter()
+            .map(|mapping| mapping.pattern),
+    );
+    futures::stream::iter(matcher::rank_partial(partial, alts.iter()))
+}
+
 /// autocomplete the known files that don't have mappings
 async fn autocomplete_alt<'a>(
     _ctx: Context<'_>,
@@ -156,28 +293,31 @@ async fn autocomplete_alt<'a>(
     } else {
         missing_mappings = Vec::new();
     }
-    futures::stream::iter(missing_mappings.clone())
-        .filter(move |name| futures::future::ready(name.contains(partial)))
-        .map(|name| name.to_string())
-        .take(10)
+    futures::stream::iter(matcher::rank_partial(partial, missing_mappings.iter()))
 }
 
-#[allow(dead_code)]
-/// autocomplete the known folders
+/// autocomplete the known folders, showing the folder names with their original case
 async fn autocomplete_og<'a>(
     _ctx: Context<'a>,
     partial: &'a str,
-) -> impl Stream<Item = String> + 'a {
+) -> impl Stream<Item = AutocompleteChoice> + 'a {
     let directories: HashMap<String, PathBuf>;
     if let Some(shared_data) = &_ctx.data().shared_thread_infos {
         directories = shared_data.lock().unwrap().og_directories.clone();
     } else {
         directories = HashMap::new();
     }
-    futures::stream::iter(directories.clone().into_iter())
-        .filter(move |(name, _)| futures::future::ready(name.contains(partial)))
-        .map(|(name, _)| name.to_string())
-        .take(10)
+    let choices: Vec<AutocompleteChoice> = matcher::rank_partial(partial, directories.keys())
+        .into_iter()
+        .map(|name| {
+            let display_name = directories[&name]
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().to_string())
+                .unwrap_or(name.clone());
+            AutocompleteChoice::new(display_name, name)
+        })
+        .collect();
+    futures::stream::iter(choices)
 }
 
 /// Will add a new Mapping to the Bot
@@ -185,44 +325,288 @@ async fn autocomplete_og<'a>(
 pub async fn new(
     ctx: Context<'_>,
     #[description = "alternative name"]
-    // #[autocomplete = "autocomplete_alt"]
+    #[autocomplete = "autocomplete_alt"]
     mut alt: String,
     #[description = "series name on server"]
-    // #[autocomplete = "autocomplete_og"]
+    #[autocomplete = "autocomplete_og"]
     mut og: String,
 ) -> Result<(), Error> {
-    alt = alt.to_lowercase();
+    alt = parser::normalize_title(alt.as_str());
     og = og.to_lowercase();
-    if let Some(shared_data) = &ctx.data().shared_thread_infos {
-        if shared_data
-            .lock()
-            .unwrap()
-            .og_directories
-            .contains_key(og.as_str())
-        {
-            info!("Adding new Mapping");
-            let message = ctx.say("Done".to_string());
-            xml::add_mappings(alt.clone(), og);
-            {
-                shared_data
-                    .lock()
-                    .unwrap()
-                    .missing_mappings
-                    .retain(|x| x.deref() != alt);
-            }
-            if let Some(tx) = &ctx.data().tx {
-                tx.send(download_watcher::SIGNAL_NEW_MAPPING)?;
-            }
-            message.await?;
-        } else {
-            warn!("Mapping could not be added");
-            ctx.say(format!("Don't know `{}` please try again.", og))
+    match add_mapping(ctx.data(), alt, og)? {
+        Ok(()) => ctx.say("Done".to_string()),
+        Err(message) => ctx.say(message),
+    }
+    .await?;
+    Ok(())
+}
+
+/// The kinds of patterns a Mapping can have
+#[derive(poise::ChoiceParameter)]
+pub enum PatternType {
+    Regex,
+    Glob,
+}
+
+/// Will get the type of the Mapping, a Mapping without a pattern type is exact
+fn get_mapping_type(pattern_type: Option<PatternType>) -> MappingType {
+    match pattern_type {
+        None => MappingType::Exact,
+        Some(PatternType::Regex) => MappingType::Regex,
+        Some(PatternType::Glob) => MappingType::Glob,
+    }
+}
+
+/// Will add a Mapping with a regex or glob that is tried after the exact Mappings
+#[poise::command(slash_command)]
+pub async fn pattern(
+    ctx: Context<'_>,
+    #[description = "regex or glob (* and ?) matched against the normalized title"] pattern: String,
+    #[description = "series name on server"]
+    #[autocomplete = "autocomplete_og"]
+    og: String,
+    #[description = "kind of the pattern"] pattern_type: PatternType,
+    #[description = "season to use instead of the one in the file name"] season: Option<i32>,
+    #[description = "number to add to every episode"] episode_offset: Option<i32>,
+    #[description = "treat the episodes as absolute episodes"] absolute: Option<bool>,
+) -> Result<(), Error> {
+    let og = og.to_lowercase();
+    let mapping_type = get_mapping_type(Some(pattern_type));
+    if let Err(message) = check_og(ctx.data(), &og) {
+        ctx.say(message).await?;
+        return Ok(());
+    }
+    if let Err(err) = pattern_mapping::compile(mapping_type, &pattern) {
+        ctx.say(format!("`{}` is not a valid {}: {}", pattern, mapping_type, err))
+            .await?;
+        return Ok(());
+    }
+    info!("Adding new {} Mapping", mapping_type);
+    xml::add_pattern_mapping(PatternMapping {
+        pattern,
+        mapping_type,
+        og,
+        numbering: Numbering {
+            season,
+            episode_offset: episode_offset.unwrap_or(0),
+            absolute: absolute.unwrap_or(false),
+        },
+    });
+    send_new_mapping_signal(ctx.data())?;
+    ctx.say("Done").await?;
+    Ok(())
+}
+
+/// Will change the series of a Mapping
+#[poise::command(slash_command)]
+pub async fn edit(
+    ctx: Context<'_>,
+    #[description = "alternative name"]
+    #[autocomplete = "autocomplete_mapping"]
+    alt: String,
+    #[description = "new series name on server"]
+    #[autocomplete = "autocomplete_og"]
+    og: String,
+    #[description = "kind of the pattern, empty for an exact Mapping"]
+    pattern_type: Option<PatternType>,
+) -> Result<(), Error> {
+    let og = og.to_lowercase();
+    if let Err(message) = check_og(ctx.data(), &og) {
+        ctx.say(message).await?;
+        return Ok(());
+    }
+    if xml::update_mapping(&alt, get_mapping_type(pattern_type), &og) {
+        send_new_mapping_signal(ctx.data())?;
+        ctx.say(format!("`{}` is now mapped to `{}`", alt, og))
+            .await?;
+    } else {
+        ctx.say(format!("There is no mapping for `{}`", alt)).await?;
+    }
+    Ok(())
+}
+
+/// Will find a video in the download folders that gets an episode smaller than 1 by the numbering
+fn find_episode_below_one(
+    data: &Data,
+    alt: &str,
+    mapping_type: MappingType,
+    numbering: &Numbering,
+) -> Option<String> {
+    let download_folders = match &data.shared_thread_infos {
+        None => return None,
+        Some(shared_data) => shared_data.lock().unwrap().download_folders.clone(),
+    };
+    let pattern = match mapping_type {
+        MappingType::Exact => None,
+        _ => pattern_mapping::compile(mapping_type, alt).ok(),
+    };
+    let alt = parser::normalize_title(alt);
+    download_watcher::get_downloaded_releases(&download_folders)
+        .into_iter()
+        .find(|(_, parsed_release)| {
+            let is_mapped = match &pattern {
+                None => parsed_release.title == alt,
+                Some(pattern) => pattern.is_match(&parsed_release.title).unwrap_or(false),
+            };
+            is_mapped
+                && numbering
+                    .translate(parsed_release.season, &parsed_release.episodes)
+                    .is_none()
+        })
+        .map(|(name, _)| name)
+}
+
+/// Will change how the season and episodes of a Mapping are translated, empty options are reset
+#[poise::command(slash_command)]
+pub async fn numbering(
+    ctx: Context<'_>,
+    #[description = "alternative name"]
+    #[autocomplete = "autocomplete_mapping"]
+    alt: String,
+    #[description = "season to use instead of the one in the file name"] season: Option<i32>,
+    #[description = "number to add to every episode"] episode_offset: Option<i32>,
+    #[description = "treat the episodes as absolute episodes"] absolute: Option<bool>,
+    #[description = "kind of the pattern, empty for an exact Mapping"]
+    pattern_type: Option<PatternType>,
+) -> Result<(), Error> {
+    let mapping_type = get_mapping_type(pattern_type);
+    let numbering = Numbering {
+        season,
+        episode_offset: episode_offset.unwrap_or(0),
+        absolute: absolute.unwrap_or(false),
+    };
+    if let Some(name) = find_episode_below_one(ctx.data(), &alt, mapping_type, &numbering) {
+        ctx.say(format!(
+            "The episode offset {} makes an episode of `{}` smaller than 1",
+            numbering.episode_offset, name
+        ))
+        .await?;
+        return Ok(());
+    }
+    if xml::update_numbering(&alt, mapping_type, &numbering) {
+        send_new_mapping_signal(ctx.data())?;
+        if numbering == Numbering::default() {
+            ctx.say(format!("`{}` uses the numbering of the file name", alt))
                 .await?;
+        } else {
+            ctx.say(format!("`{}` now uses {}", alt, numbering)).await?;
+        }
+    } else {
+        ctx.say(format!("There is no mapping for `{}`", alt)).await?;
+    }
+    Ok(())
+}
+
+/// Will change the alternative name of a Mapping
+#[poise::command(slash_command)]
+pub async fn rename(
+    ctx: Context<'_>,
+    #[description = "alternative name"]
+    #[autocomplete = "autocomplete_mapping"]
+    alt: String,
+    #[description = "new alternative name"] new_alt: String,
+    #[description = "kind of the pattern, empty for an exact Mapping"]
+    pattern_type: Option<PatternType>,
+) -> Result<(), Error> {
+    let mapping_type = get_mapping_type(pattern_type);
+    let (new_alt, exists) = match mapping_type {
+        MappingType::Exact => {
+            let new_alt = parser::normalize_title(new_alt.as_str());
+            let exists = xml::get_mappings()
+                .keys()
+                .any(|existing| parser::normalize_title(existing) == new_alt);
+            (new_alt, exists)
+        }
+        // patterns are kept as they are, normalizing would change what they match
+        _ => {
+            if let Err(err) = pattern_mapping::compile(mapping_type, &new_alt) {
+                ctx.say(format!("`{}` is not a valid {}: {}", new_alt, mapping_type, err))
+                    .await?;
+                return Ok(());
+            }
+            let exists = xml::get_pattern_mappings().iter().any(|existing| {
+                existing.mapping_type == mapping_type && existing.pattern == new_alt
+            });
+            (new_alt, exists)
         }
+    };
+    if exists {
+        ctx.say(format!("There is already a mapping for `{}`", new_alt))
+            .await?;
+        return Ok(());
+    }
+    if xml::rename_mapping(&alt, mapping_type, &new_alt) {
+        send_new_mapping_signal(ctx.data())?;
+        ctx.say(format!("Renamed `{}` to `{}`", alt, new_alt))
+            .await?;
+    } else {
+        ctx.say(format!("There is no mapping for `{}`", alt)).await?;
+    }
+    Ok(())
+}
+
+/// Will delete a Mapping
+#[poise::command(slash_command)]
+pub async fn delete(
+    ctx: Context<'_>,
+    #[description = "alternative name"]
+    #[autocomplete = "autocomplete_mapping"]
+    alt: String,
+    #[description = "kind of the pattern, empty for an exact Mapping"]
+    pattern_type: Option<PatternType>,
+) -> Result<(), Error> {
+    if xml::remove_mapping(&alt, get_mapping_type(pattern_type)) {
+        send_new_mapping_signal(ctx.data())?;
+        ctx.say(format!("Deleted the mapping for `{}`", alt))
+            .await?;
     } else {
-        warn!("Mapping Thread not started");
-        ctx.say("Mapping Thread not started".to_string()).await?;
+        ctx.say(format!("There is no mapping for `{}`", alt)).await?;
+    }
+    Ok(())
+}
+
+/// Will check that the series folder is known
+fn check_og(data: &Data, og: &str) -> Result<(), String> {
+    match &data.shared_thread_infos {
+        None => {
+            warn!("Mapping Thread not started");
+            Err("Mapping Thread not started".to_string())
+        }
+        Some(shared_data) => {
+            if shared_data.lock().unwrap().og_directories.contains_key(og) {
+                Ok(())
+            } else {
+                warn!("Series folder {} is not known", og);
+                Err(format!("Don't know `{}` please try again.", og))
+            }
+        }
     }
+}
 
+/// Will tell the Download Watcher that the Mappings changed
+fn send_new_mapping_signal(data: &Data) -> Result<(), Error> {
+    if let Some(tx) = &data.tx {
+        tx.send(download_watcher::SIGNAL_NEW_MAPPING)?;
+    }
     Ok(())
 }
+
+/// Will add the Mapping if the series is known and tell the Download Watcher about it
+///
+/// Returns the message for the user if the Mapping could not be added
+pub fn add_mapping(data: &Data, alt: String, og: String) -> Result<Result<(), String>, Error> {
+    if let Err(message) = check_og(data, &og) {
+        return Ok(Err(message));
+    }
+    info!("Adding new Mapping");
+    xml::add_mappings(alt.clone(), og);
+    if let Some(shared_data) = &data.shared_thread_infos {
+        shared_data
+            .lock()
+            .unwrap()
+            .missing_mappings
+            .retain(|x| x.deref() != alt);
+    }
+    send_new_mapping_signal(data)?;
+    Ok(Ok(()))
+}
@@ -0,0 +1,151 @@
+use log::{info, warn};
+use poise::serenity_prelude as serenity;
+use poise::serenity_prelude::{
+    ComponentInteraction, ComponentInteractionDataKind, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+
+use crate::bot::commands::add_mapping;
+use crate::bot::{Data, Error};
+use crate::download_watcher::mapping_prompt;
+use crate::xml;
+
+/// Handles the events that are not commands
+pub async fn event_handler(
+    ctx: &serenity::Context,
+    event: &serenity::FullEvent,
+    _framework: poise::FrameworkContext<'_, Data, Error>,
+    data: &Data,
+) -> Result<(), Error> {
+    if let serenity::FullEvent::InteractionCreate {
+        interaction: serenity::Interaction::Component(component),
+    } = event
+    {
+        handle_mapping_prompt(ctx, component, data).await?;
+    }
+    Ok(())
+}
+
+/// Will handle a click on the select menu or the buttons of a mapping prompt
+async fn handle_mapping_prompt(
+    ctx: &serenity::Context,
+    component: &ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let custom_id = component.data.custom_id.as_str();
+    if ![
+        mapping_prompt::SELECT_SERIES_ID,
+        mapping_prompt::IGNORE_ID,
+        mapping_prompt::SKIP_ONCE_ID,
+    ]
+    .contains(&custom_id)
+    {
+        return Ok(());
+    }
+    let prompt = data.shared_thread_infos.as_ref().and_then(|shared_data| {
+        shared_data
+            .lock()
+            .unwrap()
+            .mapping_prompts
+            .get(&component.message.id)
+            .cloned()
+    });
+    // the file could have been moved or deleted since the last pass over the download folders
+    let prompt = match prompt {
+        Some(prompt) if !prompt.file.is_file() => {
+            if let Some(shared_data) = &data.shared_thread_infos {
+                shared_data
+                    .lock()
+                    .unwrap()
+                    .mapping_prompts
+                    .remove(&component.message.id);
+            }
+            None
+        }
+        prompt => prompt,
+    };
+    let (shared_data, prompt) = match (&data.shared_thread_infos, prompt) {
+        (Some(shared_data), Some(prompt)) => (shared_data, prompt),
+        _ => {
+            warn!("Got an answer to an unknown mapping prompt");
+            component
+                .create_response(
+                    ctx,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("This prompt is no longer active")
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+            return Ok(());
+        }
+    };
+    let message = match (custom_id, &component.data.kind) {
+        (
+            mapping_prompt::SELECT_SERIES_ID,
+            ComponentInteractionDataKind::StringSelect { values },
+        ) => match values.first() {
+            None => return Ok(()),
+            Some(og) => {
+                if let Err(message) = add_mapping(data, prompt.title.clone(), og.clone())? {
+                    // keep the prompt so that another series can be chosen
+                    component
+                        .create_response(
+                            ctx,
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .content(message)
+                                    .ephemeral(true),
+                            ),
+                        )
+                        .await?;
+                    return Ok(());
+                }
+                format!("Mapped `{}` to `{}`", prompt.title, og)
+            }
+        },
+        (mapping_prompt::IGNORE_ID, _) => {
+            info!("Ignoring {} from now on", prompt.title);
+            xml::add_ignored_title(prompt.title.clone());
+            shared_data
+                .lock()
+                .unwrap()
+                .missing_mappings
+                .retain(|title| *title != prompt.title);
+            format!("Ignoring `{}` from now on", prompt.title)
+        }
+        _ => {
+            info!("Skipping {} once", prompt.file.display());
+            shared_data
+                .lock()
+                .unwrap()
+                .skipped_files
+                .push(prompt.file.clone());
+            format!(
+                "Skipped `{}` once",
+                prompt
+                    .file
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default()
+            )
+        }
+    };
+    shared_data
+        .lock()
+        .unwrap()
+        .mapping_prompts
+        .remove(&component.message.id);
+    component
+        .create_response(
+            ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(CreateEmbed::default().description(message))
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+    Ok(())
+}
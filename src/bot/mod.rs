@@ -10,6 +10,7 @@ use crate::{download_watcher, xml};
 use crate::download_watcher::ThreadInfos;
 
 mod commands;
+mod events;
 
 /// User data, which is stored and accessible in all command invocations
 pub struct Data {
@@ -31,6 +32,7 @@ pub async fn entrypoint() {
                 commands::reload_slash(),
                 commands::ping(),
                 commands::stop(),
+                commands::status(),
                 commands::map(),
             ],
             allowed_mentions: Some({
@@ -45,6 +47,9 @@ pub async fn entrypoint() {
                 ))),
                 ..Default::default()
             },
+            event_handler: |ctx, event, framework, data| {
+                Box::pin(events::event_handler(ctx, event, framework, data))
+            },
             pre_command: |ctx| {
                 Box::pin(async move {
                     info!(
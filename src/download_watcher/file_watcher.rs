@@ -0,0 +1,65 @@
+use crate::download_watcher::SIGNAL_FILES_CHANGED;
+use log::{error, info, warn};
+use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex};
+
+/// Will start watching the download folder for new files
+///
+/// Every relevant path is queued in `changed_files` and the watcher thread is woken up with
+/// [`SIGNAL_FILES_CHANGED`]. The returned watcher stops watching once it is dropped.
+/// Returns None if the filesystem doesn't support notifications
+pub fn start(
+    download_folder: &Path,
+    tx: SyncSender<u8>,
+    changed_files: Arc<Mutex<Vec<PathBuf>>>,
+) -> Option<RecommendedWatcher> {
+    let mut watcher = match notify::recommended_watcher(move |result: notify::Result<Event>| {
+        match result {
+            Ok(event) => {
+                if !is_relevant_event(&event.kind) {
+                    return;
+                }
+                {
+                    let mut changed_files = changed_files.lock().unwrap();
+                    for path in event.paths {
+                        if !changed_files.contains(&path) {
+                            changed_files.push(path);
+                        }
+                    }
+                }
+                // if the channel is full a signal is already pending and will handle the files
+                let _ = tx.try_send(SIGNAL_FILES_CHANGED);
+            }
+            Err(err) => warn!("Error while watching the download folder: {:?}", err),
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            error!("Could not create the file watcher: {:?}", err);
+            return None;
+        }
+    };
+    if let Err(err) = watcher.watch(download_folder, RecursiveMode::NonRecursive) {
+        error!(
+            "Could not watch the download folder {}: {:?}",
+            download_folder.display(),
+            err
+        );
+        return None;
+    }
+    info!("Watching {} for changes", download_folder.display());
+    Some(watcher)
+}
+
+/// Checks if the event means a file appeared or was finished writing
+fn is_relevant_event(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Access(AccessKind::Close(AccessMode::Write))
+            | EventKind::Modify(ModifyKind::Name(RenameMode::To))
+    )
+}
@@ -0,0 +1,174 @@
+use log::{error, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+const IMPORTED_FILE_NAME: &str = "appdata/imported.lst";
+
+/// A download that was copied or linked into a library and left in the download folder
+#[derive(Clone, Debug, PartialEq)]
+struct ImportedFile {
+    path: PathBuf,
+    size: u64,
+}
+
+/// The imported files, saved as one line with the size and the path per file
+#[derive(Debug, Default, PartialEq)]
+struct ImportedFiles {
+    files: Vec<ImportedFile>,
+}
+
+impl ImportedFiles {
+    /// Will read the saved lines, invalid lines are skipped
+    fn parse(content: &str) -> ImportedFiles {
+        let files = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| {
+                let size_and_path = line
+                    .split_once('\t')
+                    .and_then(|(size, path)| Some((size.parse::<u64>().ok()?, path)));
+                match size_and_path {
+                    Some((size, path)) => Some(ImportedFile {
+                        path: PathBuf::from(path),
+                        size,
+                    }),
+                    None => {
+                        warn!("Invalid line in {IMPORTED_FILE_NAME}: {line}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        ImportedFiles { files }
+    }
+
+    /// Will write every file as a line
+    fn to_content(&self) -> String {
+        self.files
+            .iter()
+            .map(|file| format!("{}\t{}\n", file.size, file.path.display()))
+            .collect()
+    }
+
+    /// Checks if the file was imported with the same size
+    fn contains(&self, path: &Path, size: u64) -> bool {
+        self.files
+            .iter()
+            .any(|file| file.path == path && file.size == size)
+    }
+
+    /// Will remember the file, an older entry of the same path is replaced
+    fn add(&mut self, path: &Path, size: u64) {
+        self.remove(path);
+        self.files.push(ImportedFile {
+            path: path.to_path_buf(),
+            size,
+        });
+    }
+
+    /// Will forget the file, returns false if it wasn't known
+    fn remove(&mut self, path: &Path) -> bool {
+        let count = self.files.len();
+        self.files.retain(|file| file.path != path);
+        self.files.len() != count
+    }
+}
+
+static IMPORTED_FILES: OnceLock<Mutex<ImportedFiles>> = OnceLock::new();
+
+/// Will load the imported files from disk the first time they are needed
+fn get_imported_files<'a>() -> &'a Mutex<ImportedFiles> {
+    IMPORTED_FILES.get_or_init(|| {
+        let content = fs::read_to_string(IMPORTED_FILE_NAME).unwrap_or_default();
+        Mutex::new(ImportedFiles::parse(&content))
+    })
+}
+
+/// Will write the imported files to disk
+fn save(files: &ImportedFiles) {
+    if let Err(err) = fs::write(IMPORTED_FILE_NAME, files.to_content()) {
+        error!("Could not save {IMPORTED_FILE_NAME}: {:?}", err);
+    }
+}
+
+/// Will get the size of the file, used to notice when a download was replaced
+fn get_size(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+/// Checks if the file was already imported into a library
+pub fn contains(path: &Path) -> bool {
+    match get_size(path) {
+        None => false,
+        Some(size) => get_imported_files().lock().unwrap().contains(path, size),
+    }
+}
+
+/// Remembers that the file was imported so that it isn't processed again
+pub fn add(path: &Path) {
+    let size = match get_size(path) {
+        None => return,
+        Some(size) => size,
+    };
+    let mut files = get_imported_files().lock().unwrap();
+    files.add(path, size);
+    save(&files);
+}
+
+/// Forgets that the file was imported so that it is processed again
+pub fn remove(path: &Path) {
+    let mut files = get_imported_files().lock().unwrap();
+    if files.remove(path) {
+        save(&files);
+    }
+}
+
+/// Forgets all imported files that were removed from the download folder
+pub fn prune() {
+    let mut files = get_imported_files().lock().unwrap();
+    let count = files.files.len();
+    files.files.retain(|file| file.path.is_file());
+    if files.files.len() != count {
+        save(&files);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_added_and_removed() {
+        let path = Path::new("/downloads/Series Name - 01.mkv");
+        let mut files = ImportedFiles::default();
+        assert!(!files.contains(path, 100));
+        files.add(path, 100);
+        files.add(path, 100);
+        assert!(files.contains(path, 100));
+        assert_eq!(files.files.len(), 1);
+        let saved = ImportedFiles::parse(&files.to_content());
+        assert_eq!(saved, files);
+        assert!(files.remove(path));
+        assert!(!files.remove(path));
+        assert!(!files.contains(path, 100));
+    }
+
+    #[test]
+    fn replaced_files_are_not_imported() {
+        let path = Path::new("/downloads/Series Name - 01.mkv");
+        let mut files = ImportedFiles::default();
+        files.add(path, 100);
+        assert!(!files.contains(path, 200));
+    }
+
+    #[test]
+    fn invalid_lines_are_skipped() {
+        let files = ImportedFiles::parse(
+            "100\t/downloads/first.mkv\nnot a line\nbig\t/downloads/size.mkv\n\n200\t/downloads/second.mkv\n",
+        );
+        assert!(files.contains(Path::new("/downloads/first.mkv"), 100));
+        assert!(files.contains(Path::new("/downloads/second.mkv"), 200));
+        assert_eq!(files.files.len(), 2);
+    }
+}
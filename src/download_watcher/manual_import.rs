@@ -0,0 +1,187 @@
+use crate::download_watcher::{
+    format_numbering, get_library, imported, path_translation, sonarr_command, sonarr_index,
+    TargetVideo, ThreadInfos,
+};
+use crate::xml;
+use crate::xml::TransferMode;
+use log::{error, info, warn};
+use serde_json::{json, Value};
+use sonarr::apis::manual_import_api::api_v3_manualimport_get;
+use sonarr::models::{EpisodeResource, ManualImportResource};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// What happened to a video that was handed to Sonarr
+pub enum ImportResult {
+    /// Sonarr imports the video, contains the message for the user
+    Started(String),
+    /// Sonarr refused the video, contains the message with the reasons
+    Rejected(String),
+    /// Sonarr doesn't manage the series or couldn't be asked, the video is moved locally
+    Unmanaged,
+}
+
+/// Will get the ids of the episodes in the season, returns None if an episode is unknown
+fn get_episode_ids(
+    series_episodes: &[EpisodeResource],
+    season: i32,
+    episodes: &[i32],
+) -> Option<Vec<i32>> {
+    episodes
+        .iter()
+        .map(|episode_number| {
+            series_episodes
+                .iter()
+                .find(|episode| {
+                    episode.season_number == Some(season)
+                        && episode.episode_number == Some(*episode_number)
+                })
+                .and_then(|episode| episode.id)
+        })
+        .collect()
+}
+
+/// Will let Sonarr evaluate the video again with the series and episodes found by the Download Watcher
+async fn reprocess(item: &ManualImportResource, body: &Value) -> Option<ManualImportResource> {
+    let response = match sonarr_command::post_json("/api/v3/manualimport", &json!([body])).await {
+        Ok(response) => response,
+        Err(err) => {
+            error!("Could not reprocess the manual import: {:?}", err);
+            return None;
+        }
+    };
+    match response.json::<Vec<ManualImportResource>>().await {
+        Ok(items) => items.into_iter().next(),
+        Err(err) => {
+            // older versions of Sonarr don't answer with the reprocessed items
+            warn!("Got no reprocessed manual import: {:?}", err);
+            Some(item.clone())
+        }
+    }
+}
+
+/// Will hand the video to the manual import of Sonarr with the series and episodes that were found
+///
+/// The rejections of Sonarr are returned so that the video isn't imported against its rules
+pub async fn import(
+    series_folder: &Path,
+    source: &Path,
+    target_video: &TargetVideo,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> ImportResult {
+    let Some(series) = sonarr_index::get_series_by_path(series_folder).await else {
+        info!(
+            "{} is not managed by Sonarr, moving it locally",
+            series_folder.display()
+        );
+        return ImportResult::Unmanaged;
+    };
+    let Some(series_id) = series.id else {
+        return ImportResult::Unmanaged;
+    };
+    // Sonarr needs to find the file under its own path
+    let path = path_translation::to_sonarr(source);
+    let path = path.as_str();
+    let file_name = source
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .replace('`', "\\`");
+    let numbering = format_numbering(Some(target_video.season), &target_video.episodes);
+    let Some(series_episodes) = sonarr_index::get_episodes(series_id).await else {
+        return ImportResult::Unmanaged;
+    };
+    let Some(episode_ids) = get_episode_ids(
+        &series_episodes,
+        target_video.season,
+        &target_video.episodes,
+    ) else {
+        return ImportResult::Rejected(format!(
+            "Sonarr doesn't know the episodes `{}` of `{}` for `{}`",
+            numbering,
+            series.title.flatten().unwrap_or_default(),
+            file_name
+        ));
+    };
+    let item = match api_v3_manualimport_get(
+        xml::get_sonarr_config(),
+        Some(path),
+        None,
+        Some(series_id),
+        Some(target_video.season),
+        Some(false),
+    )
+    .await
+    {
+        Ok(items) => items
+            .into_iter()
+            .find(|item| item.path.clone().flatten().as_deref() == Some(path)),
+        Err(err) => {
+            error!("Could not get the manual import of {}: {:?}", path, err);
+            return ImportResult::Unmanaged;
+        }
+    };
+    let Some(item) = item else {
+        return ImportResult::Rejected(format!("Sonarr can't see the file `{}`", file_name));
+    };
+    let mut file = json!({
+        "path": path,
+        "seriesId": series_id,
+        "seasonNumber": target_video.season,
+        "episodeIds": episode_ids,
+        "quality": item.quality,
+        "languages": item.languages.clone().flatten(),
+        "releaseGroup": item.release_group.clone().flatten(),
+        "downloadId": item.download_id.clone().flatten(),
+        "indexerFlags": item.indexer_flags,
+        "releaseType": item.release_type,
+    });
+    let Some(item) = reprocess(&item, &file).await else {
+        return ImportResult::Unmanaged;
+    };
+    let rejections: Vec<String> = item
+        .rejections
+        .flatten()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|rejection| rejection.reason.flatten())
+        .collect();
+    if !rejections.is_empty() {
+        warn!("Sonarr rejected {}: {:?}", path, rejections);
+        return ImportResult::Rejected(format!(
+            "Sonarr rejected `{}`: {}",
+            file_name,
+            rejections.join(", ")
+        ));
+    }
+    if let Some(quality) = item.quality {
+        file["quality"] = json!(quality);
+    }
+    let transfer_mode = get_library(series_folder, shared_thread_infos)
+        .map_or(TransferMode::Move, |library| library.transfer_mode);
+    let import_mode = match transfer_mode {
+        TransferMode::Move => "move",
+        // Sonarr hardlinks instead of copying if it is enabled in its settings
+        TransferMode::Copy | TransferMode::Hardlink => "copy",
+    };
+    if !sonarr_command::post(
+        json!({"name": "ManualImport", "importMode": import_mode, "files": [file]}),
+        format!("import {}", path),
+        Some(source.to_path_buf()),
+    )
+    .await
+    {
+        return ImportResult::Unmanaged;
+    }
+    if transfer_mode != TransferMode::Move {
+        // the source stays for seeding and must not be processed again
+        imported::add(source);
+    }
+    ImportResult::Started(format!(
+        "Handed `{}` to Sonarr to import as `{}` {}.",
+        file_name,
+        series.title.flatten().unwrap_or_default(),
+        numbering
+    ))
+}
@@ -0,0 +1,142 @@
+use crate::download_watcher::ThreadInfos;
+use crate::matcher;
+use crate::parser::ParsedRelease;
+use log::error;
+use poise::serenity_prelude::{
+    ButtonStyle, ChannelId, Context, CreateActionRow, CreateButton, CreateEmbed, CreateMessage,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// The custom id of the select menu with the series folders
+pub const SELECT_SERIES_ID: &str = "mapping_prompt_series";
+/// The custom id of the button that ignores the title from now on
+pub const IGNORE_ID: &str = "mapping_prompt_ignore";
+/// The custom id of the button that skips the file once
+pub const SKIP_ONCE_ID: &str = "mapping_prompt_skip_once";
+
+/// Discord allows at most 25 options in a select menu
+const MAX_OPTIONS: usize = 25;
+/// Discord allows at most 100 characters in the value of an option
+const MAX_VALUE_LENGTH: usize = 100;
+
+/// A prompt that asks to which series folder an unknown title belongs
+#[derive(Clone)]
+pub struct MappingPrompt {
+    /// The normalized title that has no mapping
+    pub title: String,
+    /// The file that is waiting for the mapping
+    pub file: PathBuf,
+}
+
+/// Will get the series folders for the select menu, the candidates come first
+fn get_options(
+    parsed_release: &ParsedRelease,
+    candidates: &[String],
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> Vec<String> {
+    let og_directories = shared_thread_infos.lock().unwrap().og_directories.clone();
+    let mut options: Vec<String> = candidates
+        .iter()
+        .filter(|candidate| og_directories.contains_key(*candidate))
+        .cloned()
+        .collect();
+    for (folder, _) in matcher::rank(
+        &parsed_release.title,
+        parsed_release.year,
+        og_directories.keys(),
+    ) {
+        if !options.contains(&folder) {
+            options.push(folder);
+        }
+    }
+    options.retain(|option| option.len() <= MAX_VALUE_LENGTH);
+    options.truncate(MAX_OPTIONS);
+    options
+}
+
+/// Will post a prompt to choose the series of the unknown title and remember it for the bot
+pub async fn send(
+    parsed_release: &ParsedRelease,
+    file: PathBuf,
+    candidates: &[String],
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+    ctx: &Context,
+    channel: &ChannelId,
+) {
+    let mut embed = CreateEmbed::default()
+        .title(format!("Unknown title `{}`", parsed_release.title))
+        .field(
+            "Choose the series below or add a Mapping with following command:",
+            format!(
+                "`/map new alt:{} og:<series name on the server>`",
+                parsed_release.title
+            ),
+            false,
+        );
+    if !candidates.is_empty() {
+        embed = embed.field(
+            "Best candidates:",
+            candidates
+                .iter()
+                .map(|candidate| format!("`{}`", candidate))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            false,
+        );
+    }
+    let mut components = Vec::new();
+    let options = get_options(parsed_release, candidates, shared_thread_infos);
+    if !options.is_empty() {
+        components.push(CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                SELECT_SERIES_ID,
+                CreateSelectMenuKind::String {
+                    options: options
+                        .into_iter()
+                        .map(|option| CreateSelectMenuOption::new(option.clone(), option))
+                        .collect(),
+                },
+            )
+            .placeholder("Series on the server"),
+        ));
+    }
+    components.push(CreateActionRow::Buttons(vec![
+        CreateButton::new(IGNORE_ID)
+            .label("Ignore")
+            .style(ButtonStyle::Danger),
+        CreateButton::new(SKIP_ONCE_ID)
+            .label("Skip once")
+            .style(ButtonStyle::Secondary),
+    ]));
+    match channel
+        .send_message(
+            ctx,
+            CreateMessage::default().embed(embed).components(components),
+        )
+        .await
+    {
+        Ok(message) => {
+            shared_thread_infos.lock().unwrap().mapping_prompts.insert(
+                message.id,
+                MappingPrompt {
+                    title: parsed_release.title.clone(),
+                    file,
+                },
+            );
+        }
+        Err(err) => error!("Could not send the mapping prompt: {:?}", err),
+    }
+}
+
+/// Will forget the prompts whose file is gone or whose title isn't missing a Mapping anymore
+pub fn prune(thread_infos: &mut ThreadInfos) {
+    let ThreadInfos {
+        mapping_prompts,
+        missing_mappings,
+        ..
+    } = thread_infos;
+    mapping_prompts
+        .retain(|_, prompt| prompt.file.is_file() && missing_mappings.contains(&prompt.title));
+}
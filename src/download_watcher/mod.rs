@@ -1,18 +1,38 @@
 extern crate reqwest;
 
-use crate::xml;
+mod file_watcher;
+mod imported;
+mod manual_import;
+pub mod mapping_prompt;
+mod path_translation;
+pub mod pattern_mapping;
+mod rescan;
+mod series_match;
+mod sonarr_command;
+mod sonarr_index;
+mod sonarr_parse;
+mod sonarr_series;
+pub mod stability;
+mod transfer;
+
+use crate::download_watcher::manual_import::ImportResult;
+use crate::download_watcher::series_match::TitleMatch;
+use crate::download_watcher::stability::StabilityTracker;
+use crate::naming::NamingContext;
+use crate::parser::ParsedRelease;
+use crate::xml::{
+    ImportMode, Library, LibraryKind, NamingMode, Numbering, TransferMode, WatchMode,
+};
+use crate::{matcher, naming, parser, xml};
 use log::{error, info, warn};
 use poise::serenity_prelude as serenity;
-use poise::serenity_prelude::{CreateEmbed, CreateMessage};
-use fancy_regex::Regex;
-use serenity::{ChannelId, Context};
-use sonarr::apis::episode_api::api_v3_episode_get;
-use sonarr::apis::series_api::api_v3_series_get;
+use serenity::{ChannelId, Context, MessageId};
+use sonarr::models::{SeriesResource, SeriesTypes};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{Receiver, SyncSender};
 use std::sync::{mpsc, Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{env, thread};
 
 /// The signal the Bots sends when a new mapping was added
@@ -21,10 +41,28 @@ pub const SIGNAL_NEW_MAPPING: u8 = 1;
 pub const SIGNAL_RELOAD: u8 = 2;
 /// The signal the Bots send to tell the Thread to Stop
 pub const SIGNAL_STOP: u8 = 3;
+/// The signal the file watcher sends when files in the download folder changed
+pub const SIGNAL_FILES_CHANGED: u8 = 4;
 
 /// The emoji to prepend when an error occurs
 const ERROR_EMOJI: &str = ":x: ";
 
+/// The numbers and name parts of a video in the library
+struct TargetVideo {
+    /// The season in the library
+    season: i32,
+    /// All episodes contained in the file, in the numbering of the library
+    episodes: Vec<i32>,
+    /// The file extension
+    file_format: String,
+    /// The rule that identified the episodes
+    rule: String,
+    /// The numbers of the file name if a mapping translated them
+    original_numbering: Option<String>,
+    /// What could be parsed from the file name
+    parsed_release: Option<ParsedRelease>,
+}
+
 /// Struct containing shared Objects
 pub struct ThreadInfos {
     /// The Mappings that need to be added
@@ -33,15 +71,24 @@ pub struct ThreadInfos {
     pub duplicate_files: Vec<String>,
     /// The Directories that are present and known
     pub og_directories: HashMap<String, PathBuf>,
+    /// The files that are not ready to be moved yet
+    pub pending_files: Vec<stability::PendingFile>,
+    /// The mapping prompts that were posted, by their message
+    pub mapping_prompts: HashMap<MessageId, mapping_prompt::MappingPrompt>,
+    /// The files that should be skipped until they are removed from the download folder
+    pub skipped_files: Vec<PathBuf>,
+    /// The libraries the series folders are in
+    pub libraries: Vec<Library>,
+    /// The series types of the series folders known to Sonarr
+    pub series_types: HashMap<PathBuf, SeriesTypes>,
+    /// The folders that are watched for new videos
+    pub download_folders: Vec<PathBuf>,
 }
 
-/// Will get the necessary Paths to start the Download Watcher or None
-pub fn get_paths() -> Option<(PathBuf, PathBuf, PathBuf)> {
-    const DOWNLOAD_FOLDER_NAME: &str = "Download";
-    const SHARED_VIDEO_FOLDER_NAME: &str = "Shared Video";
-    const ANIME_FOLDER_NAME: &str = "Anime";
-    const SERIES_FOLDER_NAME: &str = "Serien";
-
+/// Will get the libraries and download folders to start the Download Watcher or None
+///
+/// Every problem is logged so that the config can be fixed in one go
+pub fn get_paths() -> Option<(Vec<Library>, Vec<PathBuf>)> {
     info!("Parsing programm Arguments");
 
     let args: Vec<String> = env::args().collect();
@@ -50,119 +97,322 @@ pub fn get_paths() -> Option<(PathBuf, PathBuf, PathBuf)> {
     } else {
         &args[1]
     });
-    if !root_path.is_dir() {
-        error!("Did not give correct Path to Public Share of Server");
-        return None;
-    }
-
-    let video_folder = root_path.join(Path::new(SHARED_VIDEO_FOLDER_NAME));
 
-    let anime_folder = video_folder.join(Path::new(ANIME_FOLDER_NAME));
-    if !anime_folder.is_dir() {
-        error!("Could not find Anime Folder");
-        return None;
+    let libraries = xml::get_libraries(root_path);
+    let download_folders = xml::get_download_folders(root_path);
+    let mut valid = true;
+    if libraries.is_empty() {
+        error!("No Library configured");
+        valid = false;
     }
-
-    let series_folder = video_folder.join(Path::new(SERIES_FOLDER_NAME));
-    if !series_folder.is_dir() {
-        error!("Could not find Series Folder");
-        return None;
+    for (index, library) in libraries.iter().enumerate() {
+        if !library.path.is_dir() {
+            error!(
+                "Could not find the folder {} of the Library {}",
+                library.path.display(),
+                library.name
+            );
+            valid = false;
+        }
+        if libraries[..index]
+            .iter()
+            .any(|other| other.name.eq_ignore_ascii_case(&library.name))
+        {
+            error!("The Library {} is configured twice", library.name);
+            valid = false;
+        }
+        if libraries[..index]
+            .iter()
+            .any(|other| other.path == library.path)
+        {
+            error!(
+                "The folder {} is used by more than one Library",
+                library.path.display()
+            );
+            valid = false;
+        }
     }
-
-    let download_folder = root_path.join(Path::new(DOWNLOAD_FOLDER_NAME));
-    if !download_folder.is_dir() {
-        error!("Could not find Download Folder");
+    for download_folder in &download_folders {
+        if !download_folder.is_dir() {
+            error!(
+                "Could not find the Download Folder {}",
+                download_folder.display()
+            );
+            valid = false;
+        }
+        if let Some(library) = libraries
+            .iter()
+            .find(|library| library.path == *download_folder)
+        {
+            error!(
+                "The Download Folder {} is also the folder of the Library {}",
+                download_folder.display(),
+                library.name
+            );
+            valid = false;
+        }
+    }
+    if !valid {
         return None;
     }
-    Some((anime_folder, series_folder, download_folder))
+    for library in &libraries {
+        info!(
+            "Using the {} Library {} at {}",
+            library.kind,
+            library.name,
+            library.path.display()
+        );
+    }
+    Some((libraries, download_folders))
 }
 
 /// The main function that the Download Watcher runs on
 #[tokio::main]
 async fn run(
     ctx: Context,
-    anime_folder: PathBuf,
-    series_folder: PathBuf,
-    download_folder: PathBuf,
+    download_folders: Vec<PathBuf>,
+    tx: SyncSender<u8>,
     rx: Receiver<u8>,
     shared_thread_infos: Arc<Mutex<ThreadInfos>>,
 ) {
     const WAIT_TIME_IN_SEC: u64 = 15;
+    // safety net in case a notification got lost
+    const RESCAN_TIME_IN_SEC: u64 = 600;
+    // how often Sonarr is asked if the commands are finished
+    const COMMAND_CHECK_TIME_IN_SEC: u64 = 5;
+    // how often Sonarr may fail to import a download before it is left alone
+    const MAX_IMPORT_ATTEMPTS: u32 = 3;
 
     let channel = ChannelId::new(xml::get_main_channel());
-    let mut directories: HashMap<String, PathBuf> = HashMap::new();
+    let mut directories: HashMap<String, (PathBuf, Numbering)> = HashMap::new();
     let mut to_ignore: Vec<PathBuf> = Vec::new();
-    get_known_directories(&anime_folder, &series_folder, &shared_thread_infos);
+    let mut import_attempts: HashMap<PathBuf, u32> = HashMap::new();
+    let mut tracker = StabilityTracker::load();
+    let changed_files: Arc<Mutex<Vec<PathBuf>>> = Arc::new(Mutex::new(Vec::new()));
+    // the watchers need to stay alive as long as the thread runs
+    let watchers = match xml::get_watch_mode() {
+        WatchMode::Notify => {
+            let notify_watchers: Option<Vec<_>> = download_folders
+                .iter()
+                .map(|download_folder| {
+                    file_watcher::start(download_folder, tx.clone(), Arc::clone(&changed_files))
+                })
+                .collect();
+            if notify_watchers.is_none() {
+                warn!("Falling back to polling the download folders");
+            }
+            notify_watchers
+        }
+        WatchMode::Poll => None,
+    };
+    let wait_time = Duration::from_secs(if watchers.is_some() {
+        RESCAN_TIME_IN_SEC
+    } else {
+        WAIT_TIME_IN_SEC
+    });
+    parser::reload();
+    path_translation::reload().await;
+    sonarr_index::refresh().await;
+    get_known_directories(&shared_thread_infos);
+    sonarr_series::load(&shared_thread_infos).await;
     get_xml_mappings(&mut directories, &shared_thread_infos);
+    let mut rescan = true;
+    let mut last_rescan = Instant::now();
     loop {
-        if !check_download_folder(
-            &directories,
-            &mut to_ignore,
-            &shared_thread_infos,
-            &download_folder,
-            &ctx,
-            &channel,
-        )
-        .await
-        {
-            if let Ok(signal) = rx.recv_timeout(Duration::from_secs(WAIT_TIME_IN_SEC)) {
-                match signal {
-                    SIGNAL_STOP => return,
-                    SIGNAL_RELOAD => {
-                        get_known_directories(&anime_folder, &series_folder, &shared_thread_infos);
-                        get_xml_mappings(&mut directories, &shared_thread_infos);
-                        shared_thread_infos.lock().unwrap().missing_mappings.clear();
-                    }
-                    SIGNAL_NEW_MAPPING => get_xml_mappings(&mut directories, &shared_thread_infos),
-                    _ => error!("Got unknown signal code: {}", signal),
+        to_ignore.append(&mut shared_thread_infos.lock().unwrap().skipped_files);
+        if rescan {
+            check_download_folder(
+                &directories,
+                &mut to_ignore,
+                &mut tracker,
+                &shared_thread_infos,
+                &download_folders,
+                &ctx,
+                &channel,
+            )
+            .await;
+            last_rescan = Instant::now();
+        }
+        rescan = true;
+        update_pending_files(&mut tracker, &shared_thread_infos);
+        let mut reply = String::new();
+        for failure in sonarr_command::check_running().await {
+            let mut message = format!("{} {}", ERROR_EMOJI, failure.message);
+            if let Some(source) = &failure.source {
+                import_attempts.retain(|path, _| path.is_file());
+                let attempts = import_attempts.entry(source.clone()).or_default();
+                *attempts += 1;
+                if *attempts < MAX_IMPORT_ATTEMPTS {
+                    // the download wasn't imported and is processed again
+                    to_ignore.retain(|path| path != source);
+                    imported::remove(source);
+                } else {
+                    message.push_str(". It is skipped until it is renamed");
                 }
             }
+            append_to_reply(&ctx, &channel, &mut reply, message).await;
+        }
+        if !reply.is_empty() {
+            let _ = channel.say(&ctx, reply).await;
+        }
+        sonarr_index::refresh_if_due().await;
+        // wake up earlier if a file could have settled by then
+        let remaining = wait_time.saturating_sub(last_rescan.elapsed());
+        let rescan_timeout = tracker
+            .next_check()
+            .map_or(remaining, |next_check| next_check.min(remaining));
+        let mut timeout = rescan_timeout;
+        if sonarr_command::is_running() {
+            timeout = timeout.min(Duration::from_secs(COMMAND_CHECK_TIME_IN_SEC));
+        }
+        match rx.recv_timeout(timeout) {
+            // a wake up for the commands only asks Sonarr, the folders are scanned when due
+            Err(_) => rescan = timeout == rescan_timeout,
+            Ok(signal) => match signal {
+                SIGNAL_STOP => return,
+                SIGNAL_RELOAD => {
+                    tracker.reload();
+                    parser::reload();
+                    path_translation::reload().await;
+                    sonarr_index::refresh().await;
+                    get_known_directories(&shared_thread_infos);
+                    sonarr_series::load(&shared_thread_infos).await;
+                    get_xml_mappings(&mut directories, &shared_thread_infos);
+                    shared_thread_infos.lock().unwrap().missing_mappings.clear();
+                }
+                SIGNAL_NEW_MAPPING => get_xml_mappings(&mut directories, &shared_thread_infos),
+                SIGNAL_FILES_CHANGED => {
+                    let changed: Vec<PathBuf> = changed_files.lock().unwrap().drain(..).collect();
+                    let files: Vec<PathBuf> = get_ready_files(changed, &mut tracker)
+                        .into_iter()
+                        .filter(|path| !to_ignore.contains(path))
+                        .collect();
+                    handle_files(
+                        files,
+                        &directories,
+                        &mut to_ignore,
+                        &shared_thread_infos,
+                        &ctx,
+                        &channel,
+                    )
+                    .await;
+                    rescan = false;
+                }
+                _ => error!("Got unknown signal code: {}", signal),
+            },
         }
     }
 }
 
-/// Gets all Directories that can be seen in the Anime and Serien directory
-fn get_known_directories(
-    anime_folder: &PathBuf,
-    series_folder: &PathBuf,
-    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
-) {
-    traverse_directory(anime_folder, shared_thread_infos);
-    traverse_directory(series_folder, shared_thread_infos);
+/// Gets all Directories that can be seen in the libraries
+fn get_known_directories(shared_thread_infos: &Arc<Mutex<ThreadInfos>>) {
+    let libraries = shared_thread_infos.lock().unwrap().libraries.clone();
+    let directories = libraries
+        .iter()
+        .flat_map(|library| traverse_directory(&library.path));
+    shared_thread_infos.lock().unwrap().og_directories =
+        path_translation::index_by_folder_name(directories);
 }
 
 /// Gets all Directories that can be seen in the specified directory
-fn traverse_directory(folder: &PathBuf, shared_thread_infos: &Arc<Mutex<ThreadInfos>>) {
-    std::fs::read_dir(folder)
-        .unwrap()
-        .map(|entry| entry.as_ref().unwrap().path())
+pub fn traverse_directory(folder: &Path) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Could not read {}: {:?}", folder.display(), err);
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
         .filter(|path| path.is_dir())
-        .for_each(|dir| {
-            shared_thread_infos.lock().unwrap().og_directories.insert(
-                dir.file_name().unwrap().to_str().unwrap().to_lowercase(),
-                dir,
-            );
-        });
+        .collect()
+}
+
+/// Will parse the names of the videos in the download folders, unreadable folders are skipped
+pub fn get_downloaded_releases(download_folders: &[PathBuf]) -> Vec<(String, ParsedRelease)> {
+    download_folders
+        .iter()
+        .filter_map(|download_folder| std::fs::read_dir(download_folder).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| parser::parse(&name).map(|parsed_release| (name, parsed_release)))
+        .collect()
 }
 
 /// Gets all the mapping from the config
 fn get_xml_mappings(
-    directories: &mut HashMap<String, PathBuf>,
+    directories: &mut HashMap<String, (PathBuf, Numbering)>,
     shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
 ) {
     let new_mappings = xml::get_mappings();
+    let numberings = xml::get_numberings();
+    pattern_mapping::reload(&shared_thread_infos.lock().unwrap().og_directories);
     directories.clear();
     new_mappings.iter().for_each(|(alt, og)| {
         let mutex_share = shared_thread_infos.lock().unwrap();
         match mutex_share.og_directories.get(og) {
             None => {}
             Some(path) => {
-                directories.insert(alt.to_string(), path.to_path_buf());
+                // older mappings were saved before titles were normalized
+                let numbering = numberings.get(alt).copied().unwrap_or_default();
+                directories.insert(
+                    parser::normalize_title(alt),
+                    (path.to_path_buf(), numbering),
+                );
             }
         }
     })
 }
 
+/// Will get the library that contains the series folder
+fn get_library(
+    series_folder: &Path,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> Option<Library> {
+    shared_thread_infos
+        .lock()
+        .unwrap()
+        .libraries
+        .iter()
+        .find(|library| series_folder.parent() == Some(library.path.as_path()))
+        .cloned()
+}
+
+/// Will get the local folder of a series from the path Sonarr uses for it
+///
+/// Without a translation only the name of the folder can be compared, which has to be unique in
+/// all libraries
+fn get_local_series_folder(
+    sonarr_path: &str,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> Option<PathBuf> {
+    let local_path = path_translation::to_local(sonarr_path);
+    let (og_directories, libraries) = {
+        let shared_thread_infos = shared_thread_infos.lock().unwrap();
+        (
+            shared_thread_infos.og_directories.clone(),
+            shared_thread_infos.libraries.clone(),
+        )
+    };
+    if og_directories
+        .values()
+        .any(|directory| *directory == local_path)
+    {
+        return Some(local_path);
+    }
+    if path_translation::is_translated(sonarr_path) {
+        return None;
+    }
+    // the known directories leave out names of multiple folders, the libraries are checked for them
+    let directories = og_directories.into_values().chain(
+        libraries
+            .iter()
+            .flat_map(|library| traverse_directory(&library.path)),
+    );
+    path_translation::find_by_folder_name(&local_path, directories)
+}
+
 /// Append string to reply and post reply in channel if too long
 async fn append_to_reply(ctx: &Context, channel: &ChannelId, reply: &mut String, message: String) {
     if !message.is_empty() {
@@ -175,26 +425,66 @@ async fn append_to_reply(ctx: &Context, channel: &ChannelId, reply: &mut String,
     }
 }
 
+/// Checks if the file has one of the known video extensions
+fn is_video_file(file_path: &Path) -> bool {
+    match file_path.extension() {
+        None => false,
+        Some(extension) => extension == "mp4" || extension == "mkv" || extension == "avi",
+    }
+}
+
+/// Filters out everything that is not a video file that is ready to be moved
+fn get_ready_files(paths: Vec<PathBuf>, tracker: &mut StabilityTracker) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            !tracker.is_partial(path)
+                && is_video_file(path)
+                && !imported::contains(path)
+                && tracker.is_stable(path)
+        })
+        .collect()
+}
+
+/// Refreshes the pending files that are shown to the user
+fn update_pending_files(
+    tracker: &mut StabilityTracker,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) {
+    tracker.prune();
+    shared_thread_infos.lock().unwrap().pending_files = tracker.pending_files();
+}
+
 /// Will check the download Folder and move every File possible to the correct Folder
 async fn check_download_folder(
-    directories: &HashMap<String, PathBuf>,
+    directories: &HashMap<String, (PathBuf, Numbering)>,
     to_ignore: &mut Vec<PathBuf>,
+    tracker: &mut StabilityTracker,
     shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
-    download_folder: &PathBuf,
+    download_folders: &[PathBuf],
     ctx: &Context,
     channel: &ChannelId,
-) -> bool {
+) {
+    imported::prune();
+
     // gets the available files and also refreshed the to_ignore file vector
     let mut new_to_ignore: Vec<PathBuf> = Vec::new();
     let mut files: Vec<PathBuf> = Vec::new();
-    for file in std::fs::read_dir(download_folder)
-        .unwrap()
-        .map(|entry| entry.as_ref().unwrap().path())
-        .filter(|path| path.is_file())
-        .filter(|file_path| match file_path.extension() {
-            None => false,
-            Some(extension) => extension == "mp4" || extension == "mkv" || extension == "avi",
+    let paths: Vec<PathBuf> = download_folders
+        .iter()
+        .filter_map(|download_folder| match std::fs::read_dir(download_folder) {
+            Ok(entries) => Some(entries),
+            Err(err) => {
+                error!("Could not read {}: {:?}", download_folder.display(), err);
+                None
+            }
         })
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    for file in get_ready_files(paths, tracker)
+        .into_iter()
         .filter(|file_path| {
             if to_ignore.contains(file_path) {
                 new_to_ignore.push((*file_path).clone());
@@ -208,9 +498,6 @@ async fn check_download_folder(
     to_ignore.clear();
     to_ignore.append(&mut new_to_ignore);
 
-    let pattern =
-        Regex::new(r"(?i)^(?:\[.*] *)?(.*?)(?:[ (.]+20\d{2}[ ).-]+)?(s\d+)?[- ]*(?<!-)e?(\d+)(?!-).*?(?:.*)?\.([a-zA-Z0-9]*)").unwrap();
-
     // retrieves the video names once in advance to refresh the missing_mappings hashmap
     let mut local_files: Vec<String> = Vec::new();
     for file in files.clone() {
@@ -218,32 +505,41 @@ async fn check_download_folder(
             None => continue,
             Some(string) => string,
         };
-        match pattern.captures(name.to_lowercase().as_str()) {
-            Ok(None) => {},
-            Err(_) => {},
-            Ok(Some(captures)) => {
-                let temp_video_name = captures
-                    .get(1)
-                    .unwrap()
-                    .as_str()
-                    .replace(['.', '-'], " ")
-                    .replace(", ", " ")
-                    .replace(",", " ");
-                let video_name = temp_video_name.trim().to_string();
-                local_files.push(video_name);
-            }
+        if let Some(parsed_release) = parser::parse(name) {
+            local_files.push(parsed_release.title);
         }
     }
     {
-        shared_thread_infos
-            .lock()
-            .unwrap()
+        let mut thread_infos = shared_thread_infos.lock().unwrap();
+        thread_infos
             .missing_mappings
             .retain(|name| local_files.contains(name));
+        mapping_prompt::prune(&mut thread_infos);
     }
 
+    handle_files(
+        files,
+        directories,
+        to_ignore,
+        shared_thread_infos,
+        ctx,
+        channel,
+    )
+    .await;
+}
+
+/// Will try to move every given File to the correct Folder
+async fn handle_files(
+    files: Vec<PathBuf>,
+    directories: &HashMap<String, (PathBuf, Numbering)>,
+    to_ignore: &mut Vec<PathBuf>,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+    ctx: &Context,
+    channel: &ChannelId,
+) {
     // loop that goes through every file and tries to handle it
     let mut reply = String::from("");
+    let ignored_titles = xml::get_ignored_titles();
     'file_loop: for file in files {
         let name = match file.file_name().unwrap().to_str() {
             None => {
@@ -254,165 +550,451 @@ async fn check_download_folder(
             }
             Some(string) => string,
         };
-        match pattern.captures(name.to_lowercase().as_str()) {
-            Ok(None) => {
-                warn!("File did not contain regex");
-                let message = format!(
-                    "{} `{}` did not match regex. Please adjust regex to match file name",
-                    ERROR_EMOJI, name
-                );
-                append_to_reply(ctx, channel, &mut reply, message).await;
-                to_ignore.push(file);
-            },
-            Err(err) =>  {
-                error!("{:?}", err);
-                let message = format!(
-                    "{} Problem with regex crate with file `{}`: {:?}",
-                    ERROR_EMOJI, name, err
-                );
-                append_to_reply(ctx, channel, &mut reply, message).await;
-                to_ignore.push(file);
+        let mut parsed_release = parser::parse(name);
+        if let Some(parsed_release) = &parsed_release {
+            if ignored_titles.contains(&parsed_release.title) {
+                continue 'file_loop;
             }
-            Ok(Some(captures)) => {
-                let temp_video_name = captures
-                    .get(1)
+            // if the video name is already known to be missing, don't prompt the user again
+            if shared_thread_infos
+                .lock()
+                .unwrap()
+                .missing_mappings
+                .contains(&parsed_release.title)
+            {
+                continue 'file_loop;
+            }
+        }
+
+        let mut numbering = Numbering::default();
+        let mut video_path = parsed_release.as_ref().and_then(|parsed_release| {
+            match directories.get(&parsed_release.title) {
+                Some((video_path, mapping_numbering)) => {
+                    numbering = *mapping_numbering;
+                    Some(video_path.clone())
+                }
+                None => shared_thread_infos
+                    .lock()
                     .unwrap()
-                    .as_str()
-                    .replace(['.', '-'], " ")
-                    .replace("  ", " ");
-                let video_name = temp_video_name.trim();
-                let season = captures.get(2);
-                let episode = captures.get(3).unwrap().as_str().parse::<i32>().unwrap();
-                // group 5 is all the not needed information between episode number and file ending
-                let file_format = captures.get(4).unwrap().as_str();
-
-                {
-                    // if the video name is already known to be missing, don't prompt the user again
-                    if shared_thread_infos
-                        .lock()
-                        .unwrap()
-                        .missing_mappings
-                        .contains(&video_name.to_string())
-                    {
-                        continue 'file_loop;
+                    .og_directories
+                    .get(&parsed_release.title)
+                    .cloned(),
+            }
+        });
+        // patterns are only tried after the exact mappings
+        if let (Some(parsed_release), None) = (&parsed_release, &video_path) {
+            if let Some((found_path, pattern_numbering)) =
+                pattern_mapping::find(&parsed_release.title)
+            {
+                video_path = Some(found_path);
+                numbering = pattern_numbering;
+            }
+        }
+        let mut original_numbering = None;
+        if let Some(parsed_release) = &mut parsed_release {
+            if numbering != Numbering::default() {
+                original_numbering = Some(format_numbering(
+                    parsed_release.season,
+                    &parsed_release.episodes,
+                ));
+                match numbering.translate(parsed_release.season, &parsed_release.episodes) {
+                    Some(translated) => {
+                        (parsed_release.season, parsed_release.episodes) = translated;
                     }
-                }
-
-                match directories.get(video_name) {
-                    Some(video_path) => {
-                        let (season, episode) = match season {
-                            None => match get_only_missing_episode(video_path).await {
-                                None => {
-                                    warn!("File didn't contain season and there isn't exactly one episode missing");
-                                    let message = format!(
-                                        "{} `{}` didn't contain season and there isn't exactly one episode missing. Add season to name.",
-                                        ERROR_EMOJI, name
-                                    );
-                                    append_to_reply(ctx, channel, &mut reply, message).await;
-                                    to_ignore.push(file);
-                                    continue 'file_loop;
-                                }
-                                Some(tuple) => tuple,
-                            },
-                            Some(season) => (season.as_str()[1..].parse::<i32>().unwrap(), episode),
-                        };
-                        let message = move_video(
-                            video_path,
-                            &file,
-                            season,
-                            episode,
-                            file_format,
-                            shared_thread_infos,
-                        )
-                        .await;
+                    None => {
+                        warn!("The numbering of the Mapping makes an episode smaller than 1");
+                        let message = format!(
+                            "{} The episode offset {} of `{}` makes an episode of `{}` \
+                            smaller than 1, fix it with `/map numbering`",
+                            ERROR_EMOJI, numbering.episode_offset, parsed_release.title, name
+                        );
                         append_to_reply(ctx, channel, &mut reply, message).await;
+                        to_ignore.push(file);
+                        continue 'file_loop;
                     }
-                    None => {
-                        let path = shared_thread_infos
-                            .lock()
-                            .unwrap()
-                            .og_directories
-                            .get(video_name)
-                            .cloned();
-                        match path {
-                            Some(video_path) => {
-                                let (season, episode) = match season {
-                                    None => match get_only_missing_episode(&video_path).await {
-                                        None => {
-                                            warn!("File didn't contain season and there isn't exactly one episode missing");
-                                            let message = format!(
-                                                "{} `{}` didn't contain season and there isn't exactly one episode missing. Add season to name.",
-                                                ERROR_EMOJI, name
-                                            );
-                                            append_to_reply(ctx, channel, &mut reply, message)
-                                                .await;
-                                            to_ignore.push(file);
-                                            continue 'file_loop;
-                                        }
-                                        Some(tuple) => tuple,
-                                    },
-                                    Some(season) => {
-                                        (season.as_str()[1..].parse::<i32>().unwrap(), episode)
-                                    }
-                                };
-                                let message = move_video(
-                                    &video_path,
-                                    &file,
-                                    season,
-                                    episode,
-                                    file_format,
-                                    shared_thread_infos,
-                                )
-                                .await;
-                                append_to_reply(ctx, channel, &mut reply, message).await;
-                            }
-                            None => {
-                                warn!("File name \"{}\" is not known", video_name);
-                                shared_thread_infos
-                                    .lock()
-                                    .unwrap()
-                                    .missing_mappings
-                                    .push(video_name.to_string());
-                                let _ = channel
-                                    .send_message(
-                                        ctx,
-                                        CreateMessage::default().embed(
-                                            CreateEmbed::default().field(
-                                                "Please add a Mapping with following command:",
-                                                format!(
-                                                "`/map new alt:{} og:<series name on the server>`",
-                                                video_name
-                                            ),
-                                                false,
-                                            ),
-                                        ),
-                                    )
-                                    .await;
-                            }
+                }
+            }
+        }
+        let mut candidates: Vec<String> = Vec::new();
+        if let (Some(parsed_release), None) = (&parsed_release, &video_path) {
+            match series_match::find_series(parsed_release, shared_thread_infos).await {
+                TitleMatch::Found(found_path) => video_path = Some(found_path),
+                TitleMatch::Candidates(found_candidates) => candidates = found_candidates,
+            }
+        }
+        let series_type = video_path.as_ref().and_then(|video_path| {
+            shared_thread_infos
+                .lock()
+                .unwrap()
+                .series_types
+                .get(video_path)
+                .copied()
+        });
+        let is_daily = series_type == Some(SeriesTypes::Daily);
+        let local_episodes = match (&parsed_release, &video_path) {
+            (Some(parsed_release), Some(video_path)) => {
+                resolve_episodes(video_path, parsed_release, series_type).await
+            }
+            _ => None,
+        };
+        let (video_path, season, episodes, rule) = match (
+            &parsed_release,
+            video_path,
+            local_episodes,
+        ) {
+            (Some(parsed_release), Some(video_path), Some((season, episodes))) => {
+                (video_path, season, episodes, parsed_release.rule.clone())
+            }
+            (_, video_path, _) => match sonarr_parse::parse(name, shared_thread_infos)
+                .await
+                .filter(|sonarr_match| is_same_series(&video_path, sonarr_match))
+            {
+                Some(sonarr_match) => {
+                    // Sonarr numbers the episodes itself
+                    original_numbering = None;
+                    (
+                        sonarr_match.video_path,
+                        sonarr_match.season,
+                        sonarr_match.episodes,
+                        sonarr_parse::RULE_NAME.to_string(),
+                    )
+                }
+                None => {
+                    match (&parsed_release, video_path) {
+                        (None, _) => {
+                            warn!("File did not match any parse rule");
+                            let message = format!(
+                                    "{} `{}` did not match any parse rule and Sonarr couldn't parse it. Please add a `<ParseRule>` to the config and `/reload`",
+                                    ERROR_EMOJI, name
+                                );
+                            append_to_reply(ctx, channel, &mut reply, message).await;
+                            to_ignore.push(file);
+                        }
+                        (Some(parsed_release), Some(_)) if is_daily => {
+                            warn!("No daily episode aired on the date in the file name");
+                            let message = format!(
+                                    "{} `{}` belongs to a daily series, but Sonarr has no episode that aired on the date in the name. Add a Mapping for `{}` with `/map new` and set its season with `/map numbering`.",
+                                    ERROR_EMOJI, name, parsed_release.title
+                                );
+                            append_to_reply(ctx, channel, &mut reply, message).await;
+                            to_ignore.push(file);
+                        }
+                        (Some(_), Some(_)) => {
+                            warn!("File didn't contain season, isn't a known absolute episode and there isn't exactly one episode missing");
+                            let message = format!(
+                                    "{} `{}` didn't contain season, isn't a known absolute episode and there isn't exactly one episode missing. Add season to name.",
+                                    ERROR_EMOJI, name
+                                );
+                            append_to_reply(ctx, channel, &mut reply, message).await;
+                            to_ignore.push(file);
+                        }
+                        (Some(parsed_release), None) => {
+                            let video_name = parsed_release.title.as_str();
+                            warn!("File name \"{}\" is not known", video_name);
+                            shared_thread_infos
+                                .lock()
+                                .unwrap()
+                                .missing_mappings
+                                .push(video_name.to_string());
+                            mapping_prompt::send(
+                                parsed_release,
+                                file,
+                                &candidates,
+                                shared_thread_infos,
+                                ctx,
+                                channel,
+                            )
+                            .await;
                         }
                     }
+                    continue 'file_loop;
                 }
-            }
+            },
         };
+        let file_format = match &parsed_release {
+            Some(parsed_release) => parsed_release.extension.clone(),
+            None => file
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default()
+                .to_lowercase(),
+        };
+        let target_video = TargetVideo {
+            season,
+            episodes,
+            file_format,
+            rule,
+            original_numbering,
+            parsed_release,
+        };
+        if xml::get_import_mode() == ImportMode::Sonarr {
+            match manual_import::import(&video_path, &file, &target_video, shared_thread_infos)
+                .await
+            {
+                ImportResult::Unmanaged => {}
+                ImportResult::Started(message) => {
+                    append_to_reply(ctx, channel, &mut reply, message).await;
+                    // Sonarr takes the file away once it is imported
+                    to_ignore.push(file);
+                    continue 'file_loop;
+                }
+                ImportResult::Rejected(message) => {
+                    let message = format!("{} {}", ERROR_EMOJI, message);
+                    append_to_reply(ctx, channel, &mut reply, message).await;
+                    to_ignore.push(file);
+                    continue 'file_loop;
+                }
+            }
+        }
+        let message = move_video(&video_path, &file, &target_video, shared_thread_infos).await;
+        append_to_reply(ctx, channel, &mut reply, message).await;
     }
+    rescan::flush().await;
     if !reply.is_empty() {
         let _ = channel.say(ctx, reply).await;
     }
-    false
+}
+
+/// Checks that Sonarr found the series a Mapping chose, the folder of a Mapping is never replaced
+fn is_same_series(video_path: &Option<PathBuf>, sonarr_match: &sonarr_parse::SonarrMatch) -> bool {
+    match video_path {
+        Some(video_path) if *video_path != sonarr_match.video_path => {
+            warn!(
+                "Sonarr parsed the file as {}, but it is mapped to {}",
+                sonarr_match.video_path.display(),
+                video_path.display()
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Will get the season and episodes of a parsed file saved in the given folder
+///
+/// Daily series are looked up by the air date in the file name and never guessed, the date would be
+/// read as episode numbers. Without a season the episodes are treated as absolute episodes unless
+/// the series is a standard series. If that fails and there is only one episode, the only missing
+/// episode of the last season is used
+async fn resolve_episodes(
+    video_path: &Path,
+    parsed_release: &ParsedRelease,
+    series_type: Option<SeriesTypes>,
+) -> Option<(i32, Vec<i32>)> {
+    if let (Some(SeriesTypes::Daily), Some(air_date)) = (series_type, &parsed_release.air_date) {
+        if let Some(tuple) = get_daily_episode(video_path, air_date).await {
+            return Some(tuple);
+        }
+    }
+    if let Some(season) = parsed_release.season {
+        return Some((season, parsed_release.episodes.clone()));
+    }
+    if series_type == Some(SeriesTypes::Daily) {
+        return None;
+    }
+    if series_type != Some(SeriesTypes::Standard) {
+        if let Some(tuple) = get_absolute_episodes(video_path, &parsed_release.episodes).await {
+            return Some(tuple);
+        }
+    }
+    match get_only_missing_episode(video_path).await {
+        Some((season, episode)) if parsed_release.episodes.len() == 1 => {
+            Some((season, vec![episode]))
+        }
+        _ => None,
+    }
+}
+
+/// Will get all episodes of the season that already have a file
+///
+/// Sonarr knows the files of its series whatever their names are. The files in the season folder are
+/// read with the parse rules as well, Sonarr only learns about moved files with the next rescan. A
+/// file without a season belongs to the season of its folder and a range counts for every episode
+async fn get_present_episodes(
+    destination: &Path,
+    season_destination: &Path,
+    season: i32,
+) -> Vec<i32> {
+    let mut present_episodes = Vec::new();
+    if let Some(series_id) = sonarr_index::get_series_by_path(destination)
+        .await
+        .and_then(|series| series.id)
+    {
+        if let Some(episodes) = sonarr_index::get_episodes(series_id).await {
+            present_episodes.extend(
+                episodes
+                    .iter()
+                    .filter(|episode| {
+                        episode.season_number == Some(season) && episode.has_file == Some(true)
+                    })
+                    .filter_map(|episode| episode.episode_number),
+            );
+        }
+    }
+    let entries = match std::fs::read_dir(season_destination) {
+        Ok(entries) => entries,
+        Err(_) => return present_episodes,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(parsed_release) = parser::parse(&name) {
+            if parsed_release
+                .season
+                .is_none_or(|parsed_season| parsed_season == season)
+            {
+                present_episodes.extend(parsed_release.episodes);
+            }
+        }
+    }
+    present_episodes
+}
+
+/// Will format episodes like `e01` or `e01-e03`
+fn format_episode_range(episodes: &[i32]) -> String {
+    match episodes {
+        [first, .., last] => format!("e{:02}-e{:02}", first, last),
+        _ => format!("e{:02}", episodes[0]),
+    }
+}
+
+/// Will format the numbers of a file name like `s01e01-e03`, or `e13` without a season
+fn format_numbering(season: Option<i32>, episodes: &[i32]) -> String {
+    match season {
+        None => format_episode_range(episodes),
+        Some(season) => format!("s{:02}{}", season, format_episode_range(episodes)),
+    }
+}
+
+/// Will fill the titles and absolute numbers of the episodes from Sonarr
+///
+/// Unknown episodes are left out, the name is rendered without them
+async fn add_episode_details(series: &SeriesResource, naming_context: &mut NamingContext) {
+    let Some(series_id) = series.id else {
+        return;
+    };
+    let Some(episodes) = sonarr_index::get_episodes(series_id).await else {
+        return;
+    };
+    let mut titles: Vec<String> = Vec::new();
+    for episode_number in &naming_context.episodes {
+        let Some(episode) = episodes.iter().find(|episode| {
+            episode.season_number == Some(naming_context.season)
+                && episode.episode_number == Some(*episode_number)
+        }) else {
+            warn!(
+                "Episode {} of season {} is not known to Sonarr",
+                episode_number, naming_context.season
+            );
+            continue;
+        };
+        if let Some(title) = episode.title.clone().flatten() {
+            if !titles.contains(&title) {
+                titles.push(title);
+            }
+        }
+        if let Some(absolute) = episode.absolute_episode_number.flatten() {
+            naming_context.absolute_episodes.push(absolute);
+        }
+    }
+    if !titles.is_empty() {
+        naming_context.episode_title = Some(titles.join(" + "));
+    }
+}
+
+/// Will render the season folder and the file name with the configured Naming Mode
+///
+/// The naming settings of Sonarr fall back to the templates if Sonarr can't be reached. An empty
+/// season folder means the file is saved directly in the series folder
+async fn get_target_names(
+    destination: &Path,
+    source: &Path,
+    library: Option<&Library>,
+    naming_context: &mut NamingContext,
+) -> (String, String) {
+    if xml::get_naming_mode() == NamingMode::Sonarr {
+        if let Some(sonarr_naming) = sonarr_index::get_naming().await {
+            let series = sonarr_index::get_series_by_path(destination).await;
+            let anime = match &series {
+                Some(series) => series.series_type == Some(SeriesTypes::Anime),
+                None => library.is_some_and(|library| library.kind == LibraryKind::Anime),
+            };
+            let season_folder = series.as_ref().and_then(|series| series.season_folder);
+            if let Some(series) = series {
+                naming_context.series_title = series.title.clone().flatten();
+                naming_context.year = series.year.or(naming_context.year);
+                add_episode_details(&series, naming_context).await;
+            }
+            let file_name = if sonarr_naming.rename_episodes {
+                naming::fit_file_name(naming_context, |naming_context| {
+                    sonarr_naming.file_name(naming_context, anime).unwrap()
+                })
+            } else {
+                source.file_name().unwrap().to_str().unwrap().to_string()
+            };
+            // the files of a series without season folders are saved in the series folder
+            if season_folder == Some(false) {
+                return (String::new(), file_name);
+            }
+            return (sonarr_naming.season_folder(naming_context), file_name);
+        }
+    }
+    let templates = xml::get_naming_templates(library.map(|library| library.name.as_str()));
+    if templates.needs_episode_details() {
+        if let Some(series) = sonarr_index::get_series_by_path(destination).await {
+            add_episode_details(&series, naming_context).await;
+        }
+    }
+    let file_name = naming::fit_file_name(naming_context, |naming_context| {
+        templates.file_name(naming_context)
+    });
+    (templates.season_folder(naming_context), file_name)
 }
 
 /// Will move a found video to the given destination with the correct name
 async fn move_video(
     destination: &Path,
     source: &Path,
-    season: i32,
-    episode: i32,
-    file_format: &str,
+    target_video: &TargetVideo,
     shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
 ) -> String {
-    let season_destination = destination.join(format!("Staffel {:02}", season));
+    let season = target_video.season;
+    let episodes = target_video.episodes.as_slice();
+    let series = destination
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let parsed_release = target_video.parsed_release.as_ref();
+    let mut naming_context = NamingContext {
+        year: parsed_release
+            .and_then(|parsed_release| parsed_release.year)
+            .or(matcher::split_year(&series).1),
+        series,
+        series_title: None,
+        season,
+        episodes: episodes.to_vec(),
+        absolute_episodes: Vec::new(),
+        episode_title: None,
+        release_group: parsed_release
+            .and_then(|parsed_release| parsed_release.release_group.clone()),
+        resolution: parsed_release.and_then(|parsed_release| parsed_release.resolution.clone()),
+        source: parsed_release.and_then(|parsed_release| parsed_release.source.clone()),
+        revision: parsed_release.and_then(|parsed_release| parsed_release.revision.clone()),
+        extension: target_video.file_format.clone(),
+    };
+    let library = get_library(destination, shared_thread_infos);
+    let library = library.as_ref();
+    let (season_folder, file_name) =
+        get_target_names(destination, source, library, &mut naming_context).await;
+    let season_destination = if season_folder.is_empty() {
+        destination.to_path_buf()
+    } else {
+        destination.join(season_folder)
+    };
     if !season_destination.is_dir() {
-        if let Err(err) = std::fs::create_dir(season_destination.clone()) {
+        // the series folder of a series only known to Sonarr doesn't exist yet
+        if let Err(err) = std::fs::create_dir_all(season_destination.clone()) {
             error!("{:?}", err);
             return format!(
                 "{} Something went wrong while trying to create the directory `{}`. Please look at the logs",
@@ -420,14 +1002,13 @@ async fn move_video(
         }
     }
 
-    let target = season_destination.join(format!(
-        "{} - s{:02}e{:02}.{}",
-        destination.file_name().unwrap().to_str().unwrap(),
-        season,
-        episode,
-        file_format
-    ));
-    if target.is_file() {
+    let target = season_destination.join(file_name);
+    let present_episodes = get_present_episodes(destination, &season_destination, season).await;
+    if target.is_file()
+        || episodes
+            .iter()
+            .any(|episode| present_episodes.contains(episode))
+    {
         let file_name = source.file_name().unwrap().to_str().unwrap().to_string();
         return if !shared_thread_infos
             .lock()
@@ -453,15 +1034,40 @@ async fn move_video(
             String::new()
         };
     }
-    match std::fs::rename(source, target.clone()) {
-        Ok(_) => {
+    let transfer_mode = library.map_or(TransferMode::Move, |library| library.transfer_mode);
+    let result = match transfer_mode {
+        TransferMode::Move => transfer::move_file(source, &target),
+        TransferMode::Copy => transfer::copy_file(source, &target),
+        TransferMode::Hardlink => transfer::hardlink_file(source, &target),
+    };
+    match result {
+        Ok(strategy) => {
             info!(
-                "Moved {} to {}",
+                "Transferred {} to {} ({})",
                 source.file_name().unwrap().to_str().unwrap(),
-                target.file_name().unwrap().to_str().unwrap()
+                target.file_name().unwrap().to_str().unwrap(),
+                strategy
             );
+            if transfer_mode != TransferMode::Move {
+                // the source stays for seeding and must not be processed again
+                imported::add(source);
+            }
+            rescan::add(destination);
+            let numbering = match &target_video.original_numbering {
+                None => String::new(),
+                Some(original_numbering) => format!(
+                    ", numbering `{}` → `{}`",
+                    original_numbering,
+                    format_numbering(Some(season), episodes)
+                ),
+            };
             format!(
-                "Moved `{}` as `{}` to known folder.",
+                "{} `{}` as `{}` to known folder (rule `{}`{}).",
+                match transfer_mode {
+                    TransferMode::Move => "Moved",
+                    TransferMode::Copy => "Copied",
+                    TransferMode::Hardlink => "Linked",
+                },
                 source
                     .file_name()
                     .unwrap()
@@ -473,105 +1079,99 @@ async fn move_video(
                     .unwrap()
                     .to_str()
                     .unwrap()
-                    .replace('`', "\\`")
+                    .replace('`', "\\`"),
+                target_video.rule,
+                numbering
             )
         }
         Err(err) => {
             error!("{:?}", err);
             format!(
-                "{} Something went wrong while trying to move the file `{}`. Please look at the logs",
-                ERROR_EMOJI, source.file_name().unwrap().to_str().unwrap()
+                "{} Something went wrong while trying to transfer the file `{}`: {}",
+                ERROR_EMOJI,
+                source.file_name().unwrap().to_str().unwrap(),
+                err
             )
         }
     }
 }
 
+/// Will find the episode of a daily series that aired on the given date like `2024-03-15`
+async fn get_daily_episode(path: &Path, air_date: &str) -> Option<(i32, Vec<i32>)> {
+    let series = sonarr_index::get_series_by_path(path).await?;
+    let episodes = sonarr_index::get_episodes(series.id?).await?;
+    let episode = episodes
+        .iter()
+        .find(|episode| episode.air_date.clone().flatten().as_deref() == Some(air_date))?;
+    Some((episode.season_number?, vec![episode.episode_number?]))
+}
+
+/// Will translate absolute episode numbers to the season and episode numbers of the series
+///
+/// All episodes need to be in the same season
+async fn get_absolute_episodes(path: &Path, absolute_episodes: &[i32]) -> Option<(i32, Vec<i32>)> {
+    let series = sonarr_index::get_series_by_path(path).await?;
+    let episodes = sonarr_index::get_episodes(series.id?).await?;
+    let mut season_number = None;
+    let mut episode_numbers = Vec::new();
+    for absolute_episode in absolute_episodes {
+        let episode = episodes
+            .iter()
+            .find(|episode| episode.absolute_episode_number.flatten() == Some(*absolute_episode))?;
+        if season_number.is_some_and(|season_number| Some(season_number) != episode.season_number) {
+            warn!(
+                "Absolute episodes {:?} span multiple seasons",
+                absolute_episodes
+            );
+            return None;
+        }
+        season_number = episode.season_number;
+        episode_numbers.push(episode.episode_number?);
+    }
+    Some((season_number?, episode_numbers))
+}
+
 /// Will fetch the series and check if the newest episode is the only episode missing
 async fn get_only_missing_episode(path: &Path) -> Option<(i32, i32)> {
-    match api_v3_series_get(xml::get_sonarr_config(), None, None).await {
-        Ok(series_vec) => {
-            for series in series_vec {
-                if series.path.is_some_and(|sonarr_path| {
-                    sonarr_path.is_some_and(|sonarr_path| {
-                        Path::new(&sonarr_path).ends_with(path.file_name().unwrap_or("".as_ref()))
-                    })
-                }) {
-                    let mut seasons = series.seasons??;
-                    seasons.sort_by_key(|season| season.season_number);
-                    if seasons.last()?.statistics.clone()?.episode_count? == 0 {
-                        seasons.pop();
-                    }
-                    for season in seasons.clone() {
-                        let statistics = season.statistics.clone()?;
-                        if statistics.episode_count? == statistics.episode_file_count? {
-                            continue;
-                        }
-                        if statistics.episode_count? == statistics.episode_file_count? + 1 {
-                            if seasons.iter().position(|n| n == &season)? == seasons.len() - 1 {
-                                let season_number = season.season_number?;
-                                let series_id = series.id?;
-                                match api_v3_episode_get(
-                                    xml::get_sonarr_config(),
-                                    Some(series_id),
-                                    Some(season_number),
-                                    None,
-                                    None,
-                                    None,
-                                    None,
-                                    None,
-                                )
-                                .await
-                                {
-                                    Ok(episodes) => {
-                                        for episode in episodes {
-                                            if episode.has_file? {
-                                                continue;
-                                            }
-                                            if episode.episode_number? == statistics.episode_count?
-                                            {
-                                                return Some((
-                                                    season_number,
-                                                    episode.episode_number?,
-                                                ));
-                                            }
-                                            return None;
-                                        }
-                                    }
-                                    Err(err) => {
-                                        error!("{:?}", err);
-                                        return None;
-                                    }
-                                }
-                                return None;
-                            }
-                        }
-                    }
-                    return None;
+    let series = sonarr_index::get_series_by_path(path).await?;
+    let mut seasons = series.seasons??;
+    seasons.sort_by_key(|season| season.season_number);
+    if seasons.last()?.statistics.clone()?.episode_count? == 0 {
+        seasons.pop();
+    }
+    for season in seasons.clone() {
+        let statistics = season.statistics.clone()?;
+        if statistics.episode_count? == statistics.episode_file_count? {
+            continue;
+        }
+        if statistics.episode_count? == statistics.episode_file_count? + 1
+            && seasons.iter().position(|n| n == &season)? == seasons.len() - 1
+        {
+            let season_number = season.season_number?;
+            let series_id = series.id?;
+            let episodes = sonarr_index::get_episodes(series_id).await?;
+            for episode in episodes
+                .iter()
+                .filter(|episode| episode.season_number == Some(season_number))
+            {
+                if episode.has_file? {
+                    continue;
                 }
+                if episode.episode_number? == statistics.episode_count? {
+                    return Some((season_number, episode.episode_number?));
+                }
+                return None;
             }
-            None
-        }
-        Err(err) => {
-            error!("{:?}", err);
-            None
+            return None;
         }
     }
+    None
 }
 
 /// The entrypoint to start the download watcher thread
 pub fn entrypoint(ctx: &Context) -> Option<(SyncSender<u8>, Arc<Mutex<ThreadInfos>>)> {
     let ctx1 = ctx.clone();
-    let (anime_folder, series_folder, download_folder): (PathBuf, PathBuf, PathBuf);
-    match get_paths() {
-        None => {
-            return None;
-        }
-        Some((anime, series, download)) => {
-            anime_folder = anime;
-            series_folder = series;
-            download_folder = download;
-        }
-    }
+    let (libraries, download_folders) = get_paths()?;
 
     let (tx, rx) = mpsc::sync_channel(16);
 
@@ -579,20 +1179,20 @@ pub fn entrypoint(ctx: &Context) -> Option<(SyncSender<u8>, Arc<Mutex<ThreadInfo
         missing_mappings: Vec::new(),
         duplicate_files: Vec::new(),
         og_directories: HashMap::new(),
+        pending_files: Vec::new(),
+        mapping_prompts: HashMap::new(),
+        skipped_files: Vec::new(),
+        libraries,
+        series_types: HashMap::new(),
+        download_folders: download_folders.clone(),
     }));
 
     let infos_for_thread = Arc::clone(&shared_thread_infos);
+    let tx_for_thread = tx.clone();
     let _ = thread::Builder::new()
         .name("download_watcher".into())
         .spawn(move || {
-            run(
-                ctx1,
-                anime_folder,
-                series_folder,
-                download_folder,
-                rx,
-                infos_for_thread,
-            );
+            run(ctx1, download_folders, tx_for_thread, rx, infos_for_thread);
         });
     Some((tx, shared_thread_infos))
 }
@@ -0,0 +1,293 @@
+use crate::xml;
+use crate::xml::PathTranslation;
+use log::{error, info, warn};
+use sonarr::apis::remote_path_mapping_api::api_v3_remotepathmapping_get;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+static PATH_TRANSLATIONS: RwLock<Vec<PathTranslation>> = RwLock::new(Vec::new());
+
+/// Will load the Path Translations from the config and optionally the remote path mappings of Sonarr
+///
+/// A remote path mapping translates the path of the download client to the path of Sonarr, so it is
+/// only right for a Download Watcher that sees the downloads like the download client
+pub async fn reload() {
+    let mut translations = xml::get_path_translations();
+    if xml::get_seed_path_translations() {
+        match api_v3_remotepathmapping_get(xml::get_sonarr_config()).await {
+            Ok(mappings) => {
+                for mapping in mappings {
+                    let (Some(remote_path), Some(local_path)) =
+                        (mapping.remote_path.flatten(), mapping.local_path.flatten())
+                    else {
+                        continue;
+                    };
+                    // the configured translations win over the seeded ones
+                    if translations
+                        .iter()
+                        .any(|translation| translation.sonarr == local_path)
+                    {
+                        continue;
+                    }
+                    translations.push(PathTranslation {
+                        sonarr: local_path,
+                        local: PathBuf::from(remote_path),
+                    });
+                }
+            }
+            Err(err) => error!(
+                "Could not get the remote path mappings of Sonarr: {:?}",
+                err
+            ),
+        }
+    }
+    info!("Loaded {} path translations", translations.len());
+    *PATH_TRANSLATIONS.write().unwrap() = translations;
+}
+
+/// Will translate a path of Sonarr to the local path with the loaded Path Translations
+pub fn to_local(sonarr_path: &str) -> PathBuf {
+    translate_to_local(&PATH_TRANSLATIONS.read().unwrap(), sonarr_path)
+}
+
+/// Will translate a local path to the path Sonarr sees with the loaded Path Translations
+pub fn to_sonarr(local_path: &Path) -> String {
+    translate_to_sonarr(&PATH_TRANSLATIONS.read().unwrap(), local_path)
+}
+
+/// Checks if a loaded Path Translation applies to the path of Sonarr
+pub fn is_translated(sonarr_path: &str) -> bool {
+    has_sonarr_prefix(&PATH_TRANSLATIONS.read().unwrap(), sonarr_path)
+}
+
+/// Checks if a loaded Path Translation applies to the local path
+pub fn is_translated_local(local_path: &Path) -> bool {
+    PATH_TRANSLATIONS
+        .read()
+        .unwrap()
+        .iter()
+        .any(|translation| local_path.starts_with(&translation.local))
+}
+
+/// Will get the lowercase name of the folder, folders without a translation are compared by it
+fn get_folder_name(path: &Path) -> Option<String> {
+    path.file_name()?.to_str().map(str::to_lowercase)
+}
+
+/// Will find the only folder with the same name as the path
+///
+/// Without a translation only the name of the folder can be compared, which has to be unique in
+/// all libraries
+pub fn find_by_folder_name(
+    path: &Path,
+    folders: impl IntoIterator<Item = PathBuf>,
+) -> Option<PathBuf> {
+    let folder_name = get_folder_name(path)?;
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for folder in folders {
+        if get_folder_name(&folder) == Some(folder_name.clone()) && !candidates.contains(&folder) {
+            candidates.push(folder);
+        }
+    }
+    match candidates.len() {
+        1 => candidates.pop(),
+        0 => None,
+        _ => {
+            warn!(
+                "Multiple libraries have a folder named like {}, add a <PathTranslation>",
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Will key the folders by their lowercase name
+///
+/// A name that multiple folders have is left out instead of picking one of the folders
+pub fn index_by_folder_name(
+    folders: impl IntoIterator<Item = PathBuf>,
+) -> HashMap<String, PathBuf> {
+    let mut index: HashMap<String, PathBuf> = HashMap::new();
+    let mut ambiguous_names = HashSet::new();
+    for folder in folders {
+        let Some(folder_name) = get_folder_name(&folder) else {
+            warn!("Folder name not UTF-8: {}", folder.display());
+            continue;
+        };
+        if ambiguous_names.contains(&folder_name) {
+            continue;
+        }
+        match index.get(&folder_name) {
+            Some(other_folder) if *other_folder != folder => {
+                warn!(
+                    "{} and {} have the same name, rename one of them to map to it",
+                    other_folder.display(),
+                    folder.display()
+                );
+                index.remove(&folder_name);
+                ambiguous_names.insert(folder_name);
+            }
+            _ => {
+                index.insert(folder_name, folder);
+            }
+        }
+    }
+    index
+}
+
+/// Will split a path of Sonarr into its parts, Sonarr on Windows uses backslashes
+fn split(sonarr_path: &str) -> Vec<&str> {
+    sonarr_path
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Checks if the path of Sonarr starts with the Sonarr side of a translation
+fn has_sonarr_prefix(translations: &[PathTranslation], sonarr_path: &str) -> bool {
+    let parts = split(sonarr_path);
+    translations
+        .iter()
+        .any(|translation| parts.starts_with(&split(&translation.sonarr)))
+}
+
+/// Will translate a path of Sonarr to the local path, the longest matching prefix wins
+///
+/// Paths without a translation are used as they are
+fn translate_to_local(translations: &[PathTranslation], sonarr_path: &str) -> PathBuf {
+    let parts = split(sonarr_path);
+    translations
+        .iter()
+        .filter_map(|translation| {
+            let prefix = split(&translation.sonarr);
+            parts
+                .starts_with(&prefix)
+                .then_some((prefix.len(), translation))
+        })
+        .max_by_key(|(length, _)| *length)
+        .map_or(PathBuf::from(sonarr_path), |(length, translation)| {
+            parts[length..]
+                .iter()
+                .fold(translation.local.clone(), |path, part| path.join(part))
+        })
+}
+
+/// Will translate a local path to the path Sonarr sees, the longest matching prefix wins
+///
+/// Paths without a translation are used as they are
+fn translate_to_sonarr(translations: &[PathTranslation], local_path: &Path) -> String {
+    translations
+        .iter()
+        .filter_map(|translation| {
+            local_path
+                .strip_prefix(&translation.local)
+                .ok()
+                .map(|rest| (translation.local.components().count(), translation, rest))
+        })
+        .max_by_key(|(length, _, _)| *length)
+        .map_or(
+            local_path.to_string_lossy().to_string(),
+            |(_, translation, rest)| {
+                let separator = if translation.sonarr.contains('\\') {
+                    '\\'
+                } else {
+                    '/'
+                };
+                let mut sonarr_path = translation.sonarr.trim_end_matches(['/', '\\']).to_string();
+                for component in rest.components() {
+                    sonarr_path.push(separator);
+                    sonarr_path.push_str(&component.as_os_str().to_string_lossy());
+                }
+                sonarr_path
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translations() -> Vec<PathTranslation> {
+        vec![
+            PathTranslation {
+                sonarr: "/tv".to_string(),
+                local: PathBuf::from("/mnt/server/Shared Video"),
+            },
+            PathTranslation {
+                sonarr: "/tv/anime/".to_string(),
+                local: PathBuf::from("/mnt/anime"),
+            },
+            PathTranslation {
+                sonarr: r"D:\Series".to_string(),
+                local: PathBuf::from("/mnt/windows"),
+            },
+        ]
+    }
+
+    #[test]
+    fn longest_prefix_wins() {
+        let translations = translations();
+        assert_eq!(
+            translate_to_local(&translations, "/tv/Serien/Series Name"),
+            PathBuf::from("/mnt/server/Shared Video/Serien/Series Name")
+        );
+        assert_eq!(
+            translate_to_local(&translations, "/tv/anime/Anime Name"),
+            PathBuf::from("/mnt/anime/Anime Name")
+        );
+        assert_eq!(
+            translate_to_local(&translations, "/tvshows/Series Name"),
+            PathBuf::from("/tvshows/Series Name")
+        );
+        assert_eq!(
+            translate_to_sonarr(&translations, Path::new("/mnt/anime/Anime Name")),
+            "/tv/anime/Anime Name"
+        );
+        assert!(has_sonarr_prefix(&translations, "/tv/Serien/Series Name"));
+        assert!(!has_sonarr_prefix(&translations, "/tvshows/Series Name"));
+    }
+
+    #[test]
+    fn windows_paths_are_translated() {
+        let translations = translations();
+        assert_eq!(
+            translate_to_local(&translations, r"D:\Series\Series Name"),
+            PathBuf::from("/mnt/windows/Series Name")
+        );
+        assert_eq!(
+            translate_to_sonarr(&translations, Path::new("/mnt/windows/Series Name")),
+            r"D:\Series\Series Name"
+        );
+    }
+
+    #[test]
+    fn folder_names_used_by_multiple_libraries_are_left_out() {
+        let folders = vec![
+            PathBuf::from("/anime/Same Folder"),
+            PathBuf::from("/series/Same Folder"),
+            PathBuf::from("/series/Other Folder"),
+            PathBuf::from("/series/Other Folder"),
+            PathBuf::from("/anime/same folder"),
+        ];
+        let index = index_by_folder_name(folders.clone());
+        assert_eq!(index.len(), 1);
+        assert_eq!(
+            index.get("other folder"),
+            Some(&PathBuf::from("/series/Other Folder"))
+        );
+        assert_eq!(
+            find_by_folder_name(Path::new("/sonarr/other folder"), folders.clone()),
+            Some(PathBuf::from("/series/Other Folder"))
+        );
+        assert_eq!(
+            find_by_folder_name(Path::new("/sonarr/Same Folder"), folders.clone()),
+            None
+        );
+        assert_eq!(
+            find_by_folder_name(Path::new("/sonarr/Unknown Folder"), folders),
+            None
+        );
+    }
+}
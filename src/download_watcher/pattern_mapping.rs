@@ -0,0 +1,100 @@
+use crate::xml;
+use crate::xml::{MappingType, Numbering, PatternMapping};
+use fancy_regex::Regex;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// A regex or glob Mapping whose series folder is known
+struct CompiledMapping {
+    pattern: Regex,
+    video_path: PathBuf,
+    numbering: Numbering,
+}
+
+static PATTERN_MAPPINGS: RwLock<Vec<CompiledMapping>> = RwLock::new(Vec::new());
+
+/// Will turn a glob with `*` and `?` into a regex that matches the whole title
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for char in glob.chars() {
+        match char {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(fancy_regex::escape(char.to_string().as_str()).as_ref()),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// Will compile the pattern of the Mapping, the match is case-insensitive
+pub fn compile(mapping_type: MappingType, pattern: &str) -> Result<Regex, fancy_regex::Error> {
+    match mapping_type {
+        MappingType::Glob => Regex::new(format!("(?i){}", glob_to_regex(pattern)).as_str()),
+        _ => Regex::new(format!("(?i){}", pattern).as_str()),
+    }
+}
+
+/// Will load the regex and glob Mappings from the config, Mappings to unknown series are skipped
+pub fn reload(og_directories: &HashMap<String, PathBuf>) {
+    let mappings: Vec<CompiledMapping> = xml::get_pattern_mappings()
+        .into_iter()
+        .filter_map(|mapping: PatternMapping| {
+            let video_path = match og_directories.get(&mapping.og.to_lowercase()) {
+                None => {
+                    warn!(
+                        "Series {} of the Mapping {} is not known",
+                        mapping.og, mapping.pattern
+                    );
+                    return None;
+                }
+                Some(video_path) => video_path.clone(),
+            };
+            match compile(mapping.mapping_type, &mapping.pattern) {
+                Ok(pattern) => Some(CompiledMapping {
+                    pattern,
+                    video_path,
+                    numbering: mapping.numbering,
+                }),
+                Err(err) => {
+                    error!(
+                        "Mapping {} is not a valid {}: {:?}",
+                        mapping.pattern, mapping.mapping_type, err
+                    );
+                    None
+                }
+            }
+        })
+        .collect();
+    info!("Loaded {} pattern mappings", mappings.len());
+    *PATTERN_MAPPINGS.write().unwrap() = mappings;
+}
+
+/// Will find the first pattern Mapping that matches the normalized title
+///
+/// Returns the series folder and how the numbers of the file name are translated
+pub fn find(title: &str) -> Option<(PathBuf, Numbering)> {
+    PATTERN_MAPPINGS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|mapping| mapping.pattern.is_match(title).unwrap_or(false))
+        .map(|mapping| (mapping.video_path.clone(), mapping.numbering))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches_whole_title() {
+        let pattern = compile(MappingType::Glob, "series name * season").unwrap();
+        assert!(pattern.is_match("Series Name 2nd Season").unwrap());
+        assert!(!pattern.is_match("series name 2nd season part 2").unwrap());
+        let pattern = compile(MappingType::Glob, "series (name)?").unwrap();
+        assert!(pattern.is_match("series (name)2").unwrap());
+        assert!(!pattern.is_match("series name2").unwrap());
+    }
+}
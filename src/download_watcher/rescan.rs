@@ -0,0 +1,38 @@
+use crate::download_watcher::{sonarr_command, sonarr_index};
+use log::warn;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+static MOVED_SERIES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Remembers that a video was placed in the series folder so that Sonarr rescans it
+pub fn add(series_folder: &Path) {
+    let mut moved_series = MOVED_SERIES.lock().unwrap();
+    if !moved_series.iter().any(|folder| folder == series_folder) {
+        moved_series.push(series_folder.to_path_buf());
+    }
+}
+
+/// Will post one `RescanSeries` command per series that got videos since the last call
+pub async fn flush() {
+    let moved_series: Vec<PathBuf> = MOVED_SERIES.lock().unwrap().drain(..).collect();
+    for series_folder in moved_series {
+        let Some(series_id) = sonarr_index::get_series_by_path(&series_folder)
+            .await
+            .and_then(|series| series.id)
+        else {
+            warn!(
+                "{} is not known to Sonarr and can't be rescanned",
+                series_folder.display()
+            );
+            continue;
+        };
+        sonarr_command::post(
+            json!({"name": "RescanSeries", "seriesId": series_id}),
+            format!("rescan {}", series_folder.display()),
+            None,
+        )
+        .await;
+    }
+}
@@ -0,0 +1,123 @@
+use crate::download_watcher::{get_local_series_folder, sonarr_index, ThreadInfos};
+use crate::matcher;
+use crate::parser::ParsedRelease;
+use crate::xml;
+use log::info;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// How many candidates are suggested when a title can't be matched
+const SUGGESTION_COUNT: usize = 3;
+/// How much more similar the best folder has to be than the next one to be used automatically
+const MIN_MARGIN: f64 = 0.1;
+
+/// The result of matching a parsed title against the Sonarr series
+pub enum TitleMatch {
+    /// Exactly one series matched and its local folder is known
+    Found(PathBuf),
+    /// No series or multiple series matched, contains the local folder names of the best candidates
+    Candidates(Vec<String>),
+}
+
+/// Will find the series of the parsed title in Sonarr or in the library folders
+///
+/// The exact titles of Sonarr are tried first, the library folders are only scored if no Sonarr
+/// series has the title. The candidates of Sonarr are suggested before the most similar folders
+pub async fn find_series(
+    parsed_release: &ParsedRelease,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> TitleMatch {
+    let mut candidates = match match_title(parsed_release, shared_thread_infos).await {
+        TitleMatch::Found(video_path) => return TitleMatch::Found(video_path),
+        TitleMatch::Candidates(candidates) => candidates,
+    };
+    let folder_candidates = match match_folder(parsed_release, shared_thread_infos) {
+        TitleMatch::Found(video_path) if candidates.is_empty() => {
+            return TitleMatch::Found(video_path)
+        }
+        // multiple Sonarr series have the title, the most similar folder is only a suggestion
+        TitleMatch::Found(video_path) => video_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .into_iter()
+            .collect(),
+        TitleMatch::Candidates(folder_candidates) => folder_candidates,
+    };
+    for candidate in folder_candidates {
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates.truncate(SUGGESTION_COUNT);
+    TitleMatch::Candidates(candidates)
+}
+
+/// Will score the parsed title against every library folder
+///
+/// The best folder is only accepted if it is above the match threshold, clearly better than the next
+/// folder and has the same sequel and season numbers as the title
+fn match_folder(
+    parsed_release: &ParsedRelease,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> TitleMatch {
+    let threshold = xml::get_match_threshold();
+    let og_directories = shared_thread_infos.lock().unwrap().og_directories.clone();
+    let ranked = matcher::rank(
+        &parsed_release.title,
+        parsed_release.year,
+        og_directories.keys(),
+    );
+    match ranked.as_slice() {
+        [(folder, score), rest @ ..]
+            if *score >= threshold
+                && rest
+                    .first()
+                    .is_none_or(|(_, other)| *other < threshold && score - other >= MIN_MARGIN)
+                && matcher::has_same_numbers(&parsed_release.title, folder) =>
+        {
+            info!(
+                "Matched {} to folder {} with a similarity of {:.2}",
+                parsed_release.title, folder, score
+            );
+            TitleMatch::Found(og_directories[folder].clone())
+        }
+        _ => TitleMatch::Candidates(
+            ranked
+                .into_iter()
+                .take(SUGGESTION_COUNT)
+                .map(|(folder, _)| folder)
+                .collect(),
+        ),
+    }
+}
+
+/// Will match the parsed title against the titles and alternate titles of every Sonarr series
+async fn match_title(
+    parsed_release: &ParsedRelease,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> TitleMatch {
+    let matches: Vec<PathBuf> =
+        sonarr_index::get_series_by_title(&parsed_release.title, parsed_release.year)
+            .await
+            .into_iter()
+            .filter_map(|series| series.path.clone().flatten())
+            .filter_map(|path| get_local_series_folder(&path, shared_thread_infos))
+            .collect();
+    match matches.as_slice() {
+        [video_path] => {
+            info!(
+                "Matched {} to {} through the Sonarr titles",
+                parsed_release.title,
+                video_path.display()
+            );
+            TitleMatch::Found(video_path.clone())
+        }
+        _ => TitleMatch::Candidates(
+            matches
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .collect(),
+        ),
+    }
+}
@@ -0,0 +1,198 @@
+use crate::download_watcher::sonarr_index;
+use crate::xml;
+use log::{error, info, warn};
+use serde_json::Value;
+use sonarr::apis::command_api::api_v3_command_id_get;
+use sonarr::models::{CommandResource, CommandStatus};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// How often in a row Sonarr may not answer about a command before it is given up
+const MAX_CHECK_ERRORS: u32 = 60;
+
+/// A command that was posted to Sonarr and is not finished yet
+struct RunningCommand {
+    /// The id of the command in Sonarr
+    id: i32,
+    /// What the command does, used in the messages
+    description: String,
+    /// The series the command changes, they are refreshed when it is finished
+    series_ids: Vec<i32>,
+    /// The download the command imports
+    source: Option<PathBuf>,
+    /// How often in a row the state of the command couldn't be asked for
+    check_errors: u32,
+}
+
+/// A command that Sonarr couldn't finish
+pub struct CommandFailure {
+    /// The message for the user
+    pub message: String,
+    /// The download the command should have imported, it has to be processed again
+    pub source: Option<PathBuf>,
+}
+
+static RUNNING_COMMANDS: Mutex<Vec<RunningCommand>> = Mutex::new(Vec::new());
+
+/// Will collect the series of a command like `RescanSeries` or the files of a `ManualImport`
+fn get_series_ids(body: &Value) -> Vec<i32> {
+    let mut series_ids: Vec<i32> = body["files"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .chain([body])
+        .filter_map(|value| value["seriesId"].as_i64())
+        .filter_map(|series_id| i32::try_from(series_id).ok())
+        .collect();
+    series_ids.sort();
+    series_ids.dedup();
+    series_ids
+}
+
+/// Will post the body as JSON to the Sonarr API
+///
+/// The generated models lack fields like the `seriesId` of a command, so the request is sent with
+/// the client of the Sonarr configuration directly
+pub async fn post_json(path: &str, body: &Value) -> Result<reqwest::Response, reqwest::Error> {
+    let sonarr_config = xml::get_sonarr_config();
+    let mut request = sonarr_config
+        .client
+        .post(format!("{}{}", sonarr_config.base_path, path))
+        .json(body);
+    if let Some(api_key) = &sonarr_config.api_key {
+        request = request.header("X-Api-Key", api_key.key.as_str());
+    }
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+}
+
+/// Will post a command to Sonarr and follow it until it is finished
+///
+/// The source is the download the command imports. Returns false if the command couldn't be posted
+pub async fn post(body: Value, description: String, source: Option<PathBuf>) -> bool {
+    let response = match post_json("/api/v3/command", &body).await {
+        Ok(response) => response,
+        Err(err) => {
+            error!("Could not post the command to {}: {:?}", description, err);
+            return false;
+        }
+    };
+    match response.json::<CommandResource>().await {
+        Ok(CommandResource { id: Some(id), .. }) => {
+            info!("Sonarr will {} (command {})", description, id);
+            RUNNING_COMMANDS.lock().unwrap().push(RunningCommand {
+                id,
+                description,
+                series_ids: get_series_ids(&body),
+                source,
+                check_errors: 0,
+            });
+            true
+        }
+        Ok(_) => {
+            warn!(
+                "Sonarr didn't return an id for the command to {}",
+                description
+            );
+            true
+        }
+        Err(err) => {
+            error!(
+                "Got an invalid answer to the command to {}: {:?}",
+                description, err
+            );
+            false
+        }
+    }
+}
+
+/// Checks if a command is waiting to be finished by Sonarr
+pub fn is_running() -> bool {
+    !RUNNING_COMMANDS.lock().unwrap().is_empty()
+}
+
+/// Will ask Sonarr for the state of the running commands and forget the finished ones
+///
+/// The series of a finished command are refreshed in the index. A command Sonarr can't be asked
+/// about is asked for again, until it failed too often. Returns every command that failed
+pub async fn check_running() -> Vec<CommandFailure> {
+    let running_commands: Vec<RunningCommand> =
+        RUNNING_COMMANDS.lock().unwrap().drain(..).collect();
+    let mut still_running = Vec::new();
+    let mut failures = Vec::new();
+    for mut command in running_commands {
+        match api_v3_command_id_get(xml::get_sonarr_config(), command.id).await {
+            Ok(state) => match state.status {
+                Some(CommandStatus::Queued) | Some(CommandStatus::Started) => {
+                    command.check_errors = 0;
+                    still_running.push(command)
+                }
+                Some(CommandStatus::Completed) => {
+                    info!("Sonarr finished to {}", command.description);
+                    for series_id in command.series_ids {
+                        sonarr_index::refresh_series(series_id).await;
+                    }
+                }
+                status => {
+                    let reason = state
+                        .exception
+                        .flatten()
+                        .or(state.message.flatten())
+                        .unwrap_or_default();
+                    warn!(
+                        "Command to {} ended with {:?}: {}",
+                        command.description, status, reason
+                    );
+                    failures.push(CommandFailure {
+                        message: format!("Sonarr couldn't {}: {}", command.description, reason),
+                        source: command.source,
+                    });
+                }
+            },
+            Err(err) => {
+                error!(
+                    "Could not get the state of the command to {}: {:?}",
+                    command.description, err
+                );
+                command.check_errors += 1;
+                if command.check_errors < MAX_CHECK_ERRORS {
+                    still_running.push(command);
+                } else {
+                    failures.push(CommandFailure {
+                        message: format!(
+                            "Sonarr couldn't be asked if it finished to {}",
+                            command.description
+                        ),
+                        source: command.source,
+                    });
+                }
+            }
+        }
+    }
+    RUNNING_COMMANDS.lock().unwrap().append(&mut still_running);
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn series_ids_are_collected() {
+        assert_eq!(
+            get_series_ids(&json!({"name": "RescanSeries", "seriesId": 7})),
+            vec![7]
+        );
+        assert_eq!(
+            get_series_ids(&json!({
+                "name": "ManualImport",
+                "files": [{"seriesId": 3}, {"seriesId": 1}, {"seriesId": 3}]
+            })),
+            vec![1, 3]
+        );
+        assert!(get_series_ids(&json!({"name": "RefreshMonitoredDownloads"})).is_empty());
+    }
+}
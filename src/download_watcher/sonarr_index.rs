@@ -0,0 +1,350 @@
+use crate::download_watcher::path_translation;
+use crate::matcher;
+use crate::naming::sonarr::SonarrNaming;
+use crate::xml;
+use crate::xml::NamingMode;
+use log::{error, info};
+use sonarr::apis::episode_api::api_v3_episode_get;
+use sonarr::apis::naming_config_api::api_v3_config_naming_get;
+use sonarr::apis::series_api::{api_v3_series_get, api_v3_series_id_get};
+use sonarr::models::{EpisodeResource, SeriesResource};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// How long the series of Sonarr are used before they are loaded again
+const REFRESH_TIME: Duration = Duration::from_secs(15 * 60);
+/// How long to wait before asking Sonarr again after it couldn't be reached
+const RETRY_TIME: Duration = Duration::from_secs(60);
+
+/// The series of Sonarr and the episodes that were asked for
+#[derive(Default)]
+struct SonarrIndex {
+    /// Every series by its id
+    series: HashMap<i32, SeriesResource>,
+    /// The ids of the series by their translated local folder
+    paths: HashMap<PathBuf, i32>,
+    /// The ids of the series by every cleaned title and alternate title
+    titles: HashMap<String, Vec<i32>>,
+    /// The episodes of every season by the id of the series, only loaded when needed
+    episodes: HashMap<i32, Vec<EpisodeResource>>,
+}
+
+static INDEX: RwLock<Option<SonarrIndex>> = RwLock::new(None);
+static NAMING: RwLock<Option<SonarrNaming>> = RwLock::new(None);
+static NEXT_REFRESH: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// All titles Sonarr knows for the series
+fn get_series_titles(series: &SeriesResource) -> Vec<String> {
+    let mut titles: Vec<String> = [
+        series.title.clone(),
+        series.sort_title.clone(),
+        series.clean_title.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .flatten()
+    .collect();
+    if let Some(Some(alternate_titles)) = &series.alternate_titles {
+        titles.extend(
+            alternate_titles
+                .iter()
+                .filter_map(|alternate_title| alternate_title.title.clone().flatten()),
+        );
+    }
+    titles
+}
+
+/// Will clean a title for the lookup, a trailing year is not part of the key
+fn title_key(title: &str) -> String {
+    matcher::clean_title(&matcher::split_year(title).0)
+}
+
+impl SonarrIndex {
+    /// Will index the series by id, translated folder and title
+    fn build(series_vec: Vec<SeriesResource>, to_local: impl Fn(&str) -> PathBuf) -> SonarrIndex {
+        let mut index = SonarrIndex::default();
+        for series in series_vec {
+            index.insert(series, &to_local);
+        }
+        index
+    }
+
+    /// Will add the series to the index, a series with the same id is replaced
+    fn insert(&mut self, series: SeriesResource, to_local: impl Fn(&str) -> PathBuf) {
+        let Some(id) = series.id else {
+            return;
+        };
+        self.remove(id);
+        if let Some(sonarr_path) = series.path.clone().flatten() {
+            self.paths.insert(to_local(&sonarr_path), id);
+        }
+        for title in get_series_titles(&series) {
+            let ids = self.titles.entry(title_key(&title)).or_default();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        self.series.insert(id, series);
+    }
+
+    /// Will remove the series and its episodes from the index
+    fn remove(&mut self, id: i32) {
+        self.series.remove(&id);
+        self.episodes.remove(&id);
+        self.paths.retain(|_, other_id| *other_id != id);
+        for ids in self.titles.values_mut() {
+            ids.retain(|other_id| *other_id != id);
+        }
+    }
+
+    /// Will find the id of the series that is saved in the given folder
+    ///
+    /// Without a translation only the name of the folder can be compared, which has to be unique.
+    /// A translated path that doesn't match is in no series of Sonarr
+    fn find_by_path(&self, path: &Path, translated: bool) -> Option<i32> {
+        if let Some(id) = self.paths.get(path) {
+            return Some(*id);
+        }
+        if translated {
+            return None;
+        }
+        let local_path = path_translation::find_by_folder_name(path, self.paths.keys().cloned())?;
+        self.paths.get(&local_path).copied()
+    }
+
+    /// Will find every series with the same title, a known year has to match as well
+    fn find_by_title(&self, title: &str, year: Option<i32>) -> Vec<&SeriesResource> {
+        let Some(ids) = self.titles.get(&title_key(title)) else {
+            return Vec::new();
+        };
+        ids.iter()
+            .filter_map(|id| self.series.get(id))
+            .filter(|series| {
+                get_series_titles(series).iter().any(|other_title| {
+                    matcher::is_same_title(
+                        title,
+                        year,
+                        other_title,
+                        series.year.filter(|year| *year > 0),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Will load every series of Sonarr again and forget the loaded episodes
+///
+/// The naming settings are loaded with them if they are used. If Sonarr can't be reached the old
+/// series are kept and it is asked again after a minute
+pub async fn refresh() {
+    match api_v3_series_get(xml::get_sonarr_config(), None, None).await {
+        Ok(series_vec) => {
+            let index = SonarrIndex::build(series_vec, path_translation::to_local);
+            info!("Loaded {} series from Sonarr", index.series.len());
+            *INDEX.write().unwrap() = Some(index);
+            *NEXT_REFRESH.lock().unwrap() = Some(Instant::now() + REFRESH_TIME);
+            if xml::get_naming_mode() == NamingMode::Sonarr {
+                refresh_naming().await;
+            }
+        }
+        Err(err) => {
+            error!("Could not get the series of Sonarr: {:?}", err);
+            *NEXT_REFRESH.lock().unwrap() = Some(Instant::now() + RETRY_TIME);
+        }
+    }
+}
+
+/// Will refresh the series if they were never loaded or are too old
+pub async fn refresh_if_due() {
+    let due = NEXT_REFRESH
+        .lock()
+        .unwrap()
+        .is_none_or(|next_refresh| next_refresh <= Instant::now());
+    if due {
+        refresh().await;
+    }
+}
+
+/// Will load the naming settings of Sonarr, the old settings are kept if Sonarr can't be reached
+async fn refresh_naming() -> Option<SonarrNaming> {
+    match api_v3_config_naming_get(xml::get_sonarr_config()).await {
+        Ok(naming_config) => {
+            let naming = SonarrNaming::from(naming_config);
+            *NAMING.write().unwrap() = Some(naming.clone());
+            Some(naming)
+        }
+        Err(err) => {
+            error!("Could not get the naming settings of Sonarr: {:?}", err);
+            NAMING.read().unwrap().clone()
+        }
+    }
+}
+
+/// Will return the naming settings of Sonarr, they are loaded again with the series
+pub async fn get_naming() -> Option<SonarrNaming> {
+    refresh_if_due().await;
+    let naming = NAMING.read().unwrap().clone();
+    match naming {
+        Some(naming) => Some(naming),
+        None => refresh_naming().await,
+    }
+}
+
+/// Will load the series again after Sonarr changed it, e.g. by importing or rescanning
+///
+/// The episodes are loaded again when they are needed next
+pub async fn refresh_series(series_id: i32) {
+    match api_v3_series_id_get(xml::get_sonarr_config(), series_id, None).await {
+        Ok(series) => {
+            if let Some(index) = INDEX.write().unwrap().as_mut() {
+                index.insert(series, path_translation::to_local);
+            }
+        }
+        Err(err) => {
+            error!(
+                "Could not get the series {} of Sonarr: {:?}",
+                series_id, err
+            );
+            if let Some(index) = INDEX.write().unwrap().as_mut() {
+                index.episodes.remove(&series_id);
+            }
+        }
+    }
+}
+
+/// Will return every series of Sonarr
+pub async fn get_all_series() -> Vec<SeriesResource> {
+    refresh_if_due().await;
+    INDEX
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|index| index.series.values().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Will find the Sonarr series that is saved in the given folder
+pub async fn get_series_by_path(path: &Path) -> Option<SeriesResource> {
+    refresh_if_due().await;
+    let index = INDEX.read().unwrap();
+    let index = index.as_ref()?;
+    index
+        .find_by_path(path, path_translation::is_translated_local(path))
+        .and_then(|id| index.series.get(&id))
+        .cloned()
+}
+
+/// Will find every Sonarr series with the title or an alternate title
+pub async fn get_series_by_title(title: &str, year: Option<i32>) -> Vec<SeriesResource> {
+    refresh_if_due().await;
+    INDEX
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|index| {
+            index
+                .find_by_title(title, year)
+                .into_iter()
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Will return the episodes of every season of the series
+///
+/// They are loaded from Sonarr the first time and kept until the series changes
+pub async fn get_episodes(series_id: i32) -> Option<Vec<EpisodeResource>> {
+    if let Some(episodes) = INDEX
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|index| index.episodes.get(&series_id).cloned())
+    {
+        return Some(episodes);
+    }
+    match api_v3_episode_get(
+        xml::get_sonarr_config(),
+        Some(series_id),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await
+    {
+        Ok(episodes) => {
+            if let Some(index) = INDEX.write().unwrap().as_mut() {
+                index.episodes.insert(series_id, episodes.clone());
+            }
+            Some(episodes)
+        }
+        Err(err) => {
+            error!("Could not get the episodes from Sonarr: {:?}", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(id: i32, title: &str, year: i32, path: &str) -> SeriesResource {
+        SeriesResource {
+            id: Some(id),
+            title: Some(Some(title.to_string())),
+            year: Some(year),
+            path: Some(Some(path.to_string())),
+            ..Default::default()
+        }
+    }
+
+    fn index() -> SonarrIndex {
+        SonarrIndex::build(
+            vec![
+                series(1, "Series Name", 2010, "/tv/Series Name"),
+                series(2, "Remake", 1990, "/tv/Remake (1990)"),
+                series(3, "Remake", 2020, "/anime/Remake (2020)"),
+                series(4, "Other", 2000, "/anime/Same Folder"),
+                series(5, "Another", 2000, "/tv/Same Folder"),
+            ],
+            |sonarr_path| PathBuf::from(sonarr_path.replace("/tv", "/mnt/tv")),
+        )
+    }
+
+    #[test]
+    fn series_are_found_by_path() {
+        let index = index();
+        let find = |path, translated| index.find_by_path(Path::new(path), translated);
+        assert_eq!(find("/mnt/tv/Series Name", true), Some(1));
+        assert_eq!(find("/mnt/tv/Serien/Series Name", true), None);
+        assert_eq!(find("/other/Series Name", false), Some(1));
+        assert_eq!(find("/other/Same Folder", false), None);
+        assert_eq!(find("/mnt/tv/Same Folder", true), Some(5));
+    }
+
+    #[test]
+    fn series_are_found_by_title() {
+        let index = index();
+        let ids = |title, year| -> Vec<i32> {
+            let mut ids: Vec<i32> = index
+                .find_by_title(title, year)
+                .into_iter()
+                .filter_map(|series| series.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("series.name", None), vec![1]);
+        assert_eq!(ids("Remake", None), vec![2, 3]);
+        assert_eq!(ids("Remake", Some(2020)), vec![3]);
+        assert_eq!(ids("Remake (1990)", None), vec![2]);
+        assert!(ids("Unknown", None).is_empty());
+    }
+}
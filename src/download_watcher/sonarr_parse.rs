@@ -0,0 +1,78 @@
+use crate::download_watcher::{get_local_series_folder, ThreadInfos};
+use crate::xml;
+use log::{error, info, warn};
+use sonarr::apis::parse_api::api_v3_parse_get;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// The rule name reported when Sonarr parsed the file
+pub const RULE_NAME: &str = "sonarr";
+
+/// A file that Sonarr could assign to a series and its episodes
+pub struct SonarrMatch {
+    /// The local folder of the series
+    pub video_path: PathBuf,
+    /// The season of the episodes
+    pub season: i32,
+    /// All episodes contained in the file
+    pub episodes: Vec<i32>,
+}
+
+/// Will ask Sonarr to parse the file name and map the result to a known local folder
+pub async fn parse(
+    file_name: &str,
+    shared_thread_infos: &Arc<Mutex<ThreadInfos>>,
+) -> Option<SonarrMatch> {
+    let parse_resource =
+        match api_v3_parse_get(xml::get_sonarr_config(), Some(file_name), None).await {
+            Ok(parse_resource) => parse_resource,
+            Err(err) => {
+                error!("{:?}", err);
+                return None;
+            }
+        };
+    let series_path = match parse_resource
+        .series
+        .and_then(|series| series.path.flatten())
+    {
+        None => {
+            info!("Sonarr couldn't assign {} to a series", file_name);
+            return None;
+        }
+        Some(series_path) => series_path,
+    };
+    let video_path = match get_local_series_folder(&series_path, shared_thread_infos) {
+        None => {
+            warn!("Sonarr series folder {} is not known locally", series_path);
+            return None;
+        }
+        Some(video_path) => video_path,
+    };
+
+    let episodes = parse_resource.episodes.flatten()?;
+    let season = episodes.first()?.season_number?;
+    if episodes
+        .iter()
+        .any(|episode| episode.season_number != Some(season))
+    {
+        warn!("Sonarr assigned {} to multiple seasons", file_name);
+        return None;
+    }
+    let mut episode_numbers: Vec<i32> = episodes
+        .iter()
+        .filter_map(|episode| episode.episode_number)
+        .collect();
+    episode_numbers.sort();
+    if episode_numbers.is_empty() {
+        return None;
+    }
+    info!(
+        "Sonarr parsed {} as season {} episodes {:?} of {}",
+        file_name, season, episode_numbers, series_path
+    );
+    Some(SonarrMatch {
+        video_path,
+        season,
+        episodes: episode_numbers,
+    })
+}
@@ -0,0 +1,78 @@
+use crate::download_watcher::{path_translation, sonarr_index, traverse_directory, ThreadInfos};
+use crate::xml;
+use log::{error, info, warn};
+use sonarr::apis::root_folder_api::api_v3_rootfolder_get;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Will warn about every root folder of Sonarr that is not a library
+async fn check_root_folders(libraries: &[PathBuf]) {
+    match api_v3_rootfolder_get(xml::get_sonarr_config()).await {
+        Ok(root_folders) => {
+            for root_folder in root_folders {
+                let Some(sonarr_path) = root_folder.path.flatten() else {
+                    continue;
+                };
+                let local_path = path_translation::to_local(&sonarr_path);
+                if !libraries.contains(&local_path) {
+                    warn!(
+                        "Root folder {} of Sonarr ({}) is not a Library, add a <PathTranslation> or a <Library>",
+                        sonarr_path,
+                        local_path.display()
+                    );
+                }
+            }
+        }
+        Err(err) => error!("Could not get the root folders of Sonarr: {:?}", err),
+    }
+}
+
+/// Will add the series folders of Sonarr to the known directories and remember their series type
+///
+/// The folders are translated to local paths and only series inside a library are added, their
+/// folder doesn't need to exist yet. A folder name that is used in multiple libraries isn't known,
+/// so it is never mapped to the wrong folder. If Sonarr can't be reached only the folders on disk
+/// are known
+pub async fn load(shared_thread_infos: &Arc<Mutex<ThreadInfos>>) {
+    let libraries: Vec<PathBuf> = shared_thread_infos
+        .lock()
+        .unwrap()
+        .libraries
+        .iter()
+        .map(|library| library.path.clone())
+        .collect();
+    check_root_folders(&libraries).await;
+    let series_vec = sonarr_index::get_all_series().await;
+    let mut series_folders = Vec::new();
+    let mut series_types = HashMap::new();
+    for series in series_vec {
+        let Some(sonarr_path) = series.path.flatten() else {
+            continue;
+        };
+        let local_path = path_translation::to_local(&sonarr_path);
+        if !local_path
+            .parent()
+            .is_some_and(|parent| libraries.iter().any(|library| library == parent))
+        {
+            warn!(
+                "Series folder {} of Sonarr is not in a Library",
+                local_path.display()
+            );
+            continue;
+        }
+        if let Some(series_type) = series.series_type {
+            series_types.insert(local_path.clone(), series_type);
+        }
+        series_folders.push(local_path);
+    }
+    info!("Loaded {} series folders from Sonarr", series_folders.len());
+    let directories = libraries
+        .iter()
+        .flat_map(|library| traverse_directory(library))
+        .chain(series_folders);
+    let og_directories = path_translation::index_by_folder_name(directories);
+    let mut shared_thread_infos = shared_thread_infos.lock().unwrap();
+    shared_thread_infos.og_directories = og_directories;
+    shared_thread_infos.series_types = series_types;
+}
@@ -0,0 +1,277 @@
+use crate::xml;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Why a file in the download folder is not moved yet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PendingReason {
+    /// The file has a partial-file suffix of the download client
+    Downloading,
+    /// The size or modification time changed during the settle window
+    Settling,
+}
+
+impl Display for PendingReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PendingReason::Downloading => write!(f, "still downloading"),
+            PendingReason::Settling => write!(f, "waiting for the file to settle"),
+        }
+    }
+}
+
+/// A file that is not ready to be moved yet
+#[derive(Clone, Debug)]
+pub struct PendingFile {
+    /// The file name
+    pub name: String,
+    /// The reason the file is not moved yet
+    pub reason: PendingReason,
+}
+
+/// The size and modification time of a file the last time it was looked at
+struct Observation {
+    size: u64,
+    modified: SystemTime,
+    since: Instant,
+    stable: bool,
+}
+
+/// Keeps track of files in the download folder that are still being written
+pub struct StabilityTracker {
+    settle_time: Duration,
+    partial_suffixes: Vec<String>,
+    observations: HashMap<PathBuf, Observation>,
+    downloading: Vec<PathBuf>,
+}
+
+impl StabilityTracker {
+    /// Creates a new tracker with the settings of the config
+    pub fn load() -> StabilityTracker {
+        let mut tracker = StabilityTracker::new(Duration::ZERO, Vec::new());
+        tracker.reload();
+        tracker
+    }
+
+    /// Creates a new tracker with the given settle window and lowercase partial-file suffixes
+    fn new(settle_time: Duration, partial_suffixes: Vec<String>) -> StabilityTracker {
+        StabilityTracker {
+            settle_time,
+            partial_suffixes,
+            observations: HashMap::new(),
+            downloading: Vec::new(),
+        }
+    }
+
+    /// Reads the settle window and the partial-file suffixes again from the config
+    pub fn reload(&mut self) {
+        self.settle_time = Duration::from_secs(xml::get_settle_time());
+        self.partial_suffixes = xml::get_partial_suffixes()
+            .iter()
+            .map(|suffix| suffix.to_lowercase())
+            .collect();
+        info!(
+            "Files need to settle for {}s, partial suffixes: {:?}",
+            self.settle_time.as_secs(),
+            self.partial_suffixes
+        );
+    }
+
+    /// Checks if the file is an unfinished download and remembers it as pending if so
+    pub fn is_partial(&mut self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            None => return false,
+            Some(name) => name.to_lowercase(),
+        };
+        if self
+            .partial_suffixes
+            .iter()
+            .any(|suffix| name.ends_with(suffix.as_str()))
+        {
+            if !self.downloading.contains(&path.to_path_buf()) {
+                self.downloading.push(path.to_path_buf());
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Checks if the size and modification time of the file didn't change for the settle window
+    ///
+    /// A file is never stable the first time it is seen, even if it was last modified long ago,
+    /// since a moved or copied file can keep its old modification time while it is still written
+    pub fn is_stable(&mut self, path: &Path) -> bool {
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warn!("Could not read metadata of {}: {:?}", path.display(), err);
+                return false;
+            }
+        };
+        let size = metadata.len();
+        let modified = metadata.modified().unwrap_or(SystemTime::now());
+        match self.observations.get_mut(path) {
+            Some(observation) if observation.size == size && observation.modified == modified => {
+                observation.stable = observation.since.elapsed() >= self.settle_time;
+                observation.stable
+            }
+            _ => {
+                self.observations.insert(
+                    path.to_path_buf(),
+                    Observation {
+                        size,
+                        modified,
+                        since: Instant::now(),
+                        stable: false,
+                    },
+                );
+                false
+            }
+        }
+    }
+
+    /// Forgets every file that is not in the download folder anymore
+    pub fn prune(&mut self) {
+        self.observations.retain(|path, _| path.is_file());
+        self.downloading.retain(|path| path.is_file());
+    }
+
+    /// All files that are not ready to be moved
+    pub fn pending_files(&self) -> Vec<PendingFile> {
+        let to_pending = |path: &PathBuf, reason: PendingReason| PendingFile {
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            reason,
+        };
+        let mut pending: Vec<PendingFile> = self
+            .downloading
+            .iter()
+            .map(|path| to_pending(path, PendingReason::Downloading))
+            .collect();
+        self.observations
+            .iter()
+            .filter(|(_, observation)| !observation.stable)
+            .for_each(|(path, _)| pending.push(to_pending(path, PendingReason::Settling)));
+        pending
+    }
+
+    /// The time until the next settling file could be stable, if there is any
+    pub fn next_check(&self) -> Option<Duration> {
+        self.observations
+            .values()
+            .filter(|observation| !observation.stable)
+            .map(|observation| {
+                self.settle_time
+                    .saturating_sub(observation.since.elapsed())
+                    .max(Duration::from_secs(1))
+            })
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, OpenOptions};
+    use std::io::Write;
+
+    /// A file in a temporary folder that is deleted again at the end of the test
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            let folder = std::env::temp_dir().join(format!("stability-{}", std::process::id()));
+            std::fs::create_dir_all(&folder).unwrap();
+            let path = folder.join(name);
+            File::create(&path).unwrap().write_all(b"video").unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn settled_files_are_stable_on_the_second_look() {
+        let file = TempFile::new("settled.mkv");
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(&file.0)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let mut tracker = StabilityTracker::new(Duration::ZERO, Vec::new());
+        assert!(!tracker.is_stable(&file.0));
+        assert!(tracker.is_stable(&file.0));
+        assert!(tracker.pending_files().is_empty());
+    }
+
+    #[test]
+    fn old_files_are_not_stable_on_sight() {
+        let file = TempFile::new("old.mkv");
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(&file.0)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let mut tracker = StabilityTracker::new(Duration::from_secs(60), Vec::new());
+        assert!(!tracker.is_stable(&file.0));
+        assert!(!tracker.is_stable(&file.0));
+        assert_eq!(tracker.pending_files()[0].reason, PendingReason::Settling);
+    }
+
+    #[test]
+    fn growing_files_are_not_stable() {
+        let file = TempFile::new("growing.mkv");
+        let mut tracker = StabilityTracker::new(Duration::ZERO, Vec::new());
+        assert!(!tracker.is_stable(&file.0));
+        OpenOptions::new()
+            .append(true)
+            .open(&file.0)
+            .unwrap()
+            .write_all(b" more")
+            .unwrap();
+        assert!(!tracker.is_stable(&file.0));
+        assert!(tracker.is_stable(&file.0));
+    }
+
+    #[test]
+    fn modified_files_are_not_stable() {
+        let file = TempFile::new("modified.mkv");
+        let mut tracker = StabilityTracker::new(Duration::ZERO, Vec::new());
+        assert!(!tracker.is_stable(&file.0));
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(&file.0)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert!(!tracker.is_stable(&file.0));
+        assert!(tracker.is_stable(&file.0));
+    }
+
+    #[test]
+    fn partial_files_are_downloading() {
+        let file = TempFile::new("download.mkv.PART");
+        let mut tracker = StabilityTracker::new(Duration::ZERO, vec![".part".to_string()]);
+        assert!(tracker.is_partial(&file.0));
+        assert!(!tracker.is_partial(Path::new("/downloads/video.mkv")));
+        let pending = tracker.pending_files();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].name, "download.mkv.PART");
+        assert_eq!(pending[0].reason, PendingReason::Downloading);
+    }
+}
@@ -0,0 +1,295 @@
+use crate::download_watcher::imported;
+use crate::xml;
+use log::{info, warn};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+/// The different ways a file can get to its target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferStrategy {
+    /// Rename the file, only possible on the same filesystem
+    Rename,
+    /// Create a second hard link to the file, only possible on the same filesystem
+    Hardlink,
+    /// Share the data blocks with the source (btrfs, xfs, ...)
+    Reflink,
+    /// Copy the data and verify the copy
+    Copy,
+}
+
+impl Display for TransferStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferStrategy::Rename => write!(f, "renamed"),
+            TransferStrategy::Hardlink => write!(f, "hardlinked"),
+            TransferStrategy::Reflink => write!(f, "reflinked"),
+            TransferStrategy::Copy => write!(f, "copied"),
+        }
+    }
+}
+
+/// Will move the source to the target
+///
+/// Tries to rename the file first. If source and target are on different filesystems the file is
+/// reflinked or copied and the source is deleted once the copy is verified. A source that can't be
+/// deleted is remembered as imported so that it isn't moved a second time.
+pub fn move_file(source: &Path, target: &Path) -> io::Result<TransferStrategy> {
+    match std::fs::rename(source, target) {
+        Ok(_) => Ok(TransferStrategy::Rename),
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            info!(
+                "{} and {} are on different filesystems, copying instead",
+                source.display(),
+                target.display()
+            );
+            copy_and_remove(
+                source,
+                target,
+                xml::get_verify_checksum(),
+                |source| std::fs::remove_file(source),
+                imported::add,
+            )
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Will copy the source to the target and remove the source once the copy is verified
+///
+/// If the source can't be removed it is kept, so that it isn't moved a second time
+fn copy_and_remove(
+    source: &Path,
+    target: &Path,
+    compare_content: bool,
+    remove_source: impl FnOnce(&Path) -> io::Result<()>,
+    keep_source: impl FnOnce(&Path),
+) -> io::Result<TransferStrategy> {
+    let strategy = copy_verified(source, target, compare_content)?;
+    if let Err(err) = remove_source(source) {
+        warn!(
+            "Could not delete {} after it was {}, remembering it as imported: {:?}",
+            source.display(),
+            strategy,
+            err
+        );
+        keep_source(source);
+    }
+    Ok(strategy)
+}
+
+/// Will create a hard link of the source at the target
+///
+/// Falls back to a copy if source and target are on different filesystems
+pub fn hardlink_file(source: &Path, target: &Path) -> io::Result<TransferStrategy> {
+    match std::fs::hard_link(source, target) {
+        Ok(_) => Ok(TransferStrategy::Hardlink),
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            info!(
+                "{} and {} are on different filesystems, copying instead",
+                source.display(),
+                target.display()
+            );
+            copy_file(source, target)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Will create an independent copy of the source at the target
+///
+/// Tries to reflink the file first and falls back to a verified copy
+pub fn copy_file(source: &Path, target: &Path) -> io::Result<TransferStrategy> {
+    copy_verified(source, target, xml::get_verify_checksum())
+}
+
+/// Will reflink or copy the source next to the target and give it the final name once verified
+fn copy_verified(
+    source: &Path,
+    target: &Path,
+    compare_content: bool,
+) -> io::Result<TransferStrategy> {
+    let temp_target = get_temp_path(target);
+    let result = match reflink(source, &temp_target) {
+        Ok(_) => Ok(TransferStrategy::Reflink),
+        Err(_) => std::fs::copy(source, &temp_target).map(|_| TransferStrategy::Copy),
+    }
+    .and_then(|strategy| verify_copy(source, &temp_target, compare_content).map(|_| strategy))
+    .and_then(|strategy| std::fs::rename(&temp_target, target).map(|_| strategy));
+    if result.is_err() {
+        // never leave a half-written copy behind
+        let _ = std::fs::remove_file(&temp_target);
+    }
+    result
+}
+
+/// The path a copy is written to before it gets its final name
+fn get_temp_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    target.with_file_name(format!(".{name}.partial"))
+}
+
+/// Clones the data blocks of the source into a new file
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, target: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source_file = File::open(source)?;
+    let target_file = File::create(target)?;
+    // SAFETY: both file descriptors are valid for the duration of the call
+    let result = unsafe {
+        libc::ioctl(
+            target_file.as_raw_fd(),
+            libc::FICLONE,
+            source_file.as_raw_fd(),
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Clones the data blocks of the source into a new file
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::Error::from(ErrorKind::Unsupported))
+}
+
+/// Checks that the copy has the same size and, if enabled, the same content as the source
+fn verify_copy(source: &Path, copy: &Path, compare_content: bool) -> io::Result<()> {
+    let source_size = std::fs::metadata(source)?.len();
+    let copy_size = std::fs::metadata(copy)?.len();
+    if source_size != copy_size {
+        return Err(io::Error::other(format!(
+            "copy has {copy_size} bytes instead of {source_size}"
+        )));
+    }
+    if compare_content && !has_same_content(source, copy)? {
+        return Err(io::Error::other("content of the copy doesn't match"));
+    }
+    Ok(())
+}
+
+/// Compares the two files byte for byte, both have to be of the same size
+fn has_same_content(path: &Path, other_path: &Path) -> io::Result<bool> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut other_file = BufReader::new(File::open(other_path)?);
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut other_buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        other_file.read_exact(&mut other_buffer[..read])?;
+        if buffer[..read] != other_buffer[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A temporary folder that is deleted again at the end of the test
+    struct TempFolder(PathBuf);
+
+    impl TempFolder {
+        fn new(name: &str) -> TempFolder {
+            let path =
+                std::env::temp_dir().join(format!("transfer-{}-{}", std::process::id(), name));
+            std::fs::create_dir_all(&path).unwrap();
+            TempFolder(path)
+        }
+
+        fn file(&self, name: &str, content: &[u8]) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempFolder {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn copies_are_verified() {
+        let folder = TempFolder::new("verify");
+        let source = folder.file("source.mkv", b"video content");
+        let same = folder.file("same.mkv", b"video content");
+        let changed = folder.file("changed.mkv", b"video c0ntent");
+        let shorter = folder.file("shorter.mkv", b"video");
+        assert!(verify_copy(&source, &same, true).is_ok());
+        assert!(verify_copy(&source, &changed, false).is_ok());
+        assert!(verify_copy(&source, &changed, true).is_err());
+        assert!(verify_copy(&source, &shorter, false).is_err());
+    }
+
+    #[test]
+    fn failed_copies_are_cleaned_up() {
+        let folder = TempFolder::new("cleanup");
+        let source = folder.file("source.mkv", b"video content");
+        // a folder can't be replaced by the copy
+        let target = folder.0.join("target.mkv");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("inside"), b"").unwrap();
+        assert!(copy_verified(&source, &target, true).is_err());
+        assert!(!get_temp_path(&target).exists());
+        assert!(source.is_file());
+    }
+
+    #[test]
+    fn sources_are_removed_after_the_copy() {
+        let folder = TempFolder::new("remove");
+        let source = folder.file("source.mkv", b"video content");
+        let target = folder.0.join("target.mkv");
+        let kept = RefCell::new(Vec::new());
+        let strategy = copy_and_remove(
+            &source,
+            &target,
+            true,
+            |source| std::fs::remove_file(source),
+            |source| kept.borrow_mut().push(source.to_path_buf()),
+        )
+        .unwrap();
+        assert!(matches!(
+            strategy,
+            TransferStrategy::Copy | TransferStrategy::Reflink
+        ));
+        assert!(!source.exists());
+        assert_eq!(std::fs::read(&target).unwrap(), b"video content");
+        assert!(!get_temp_path(&target).exists());
+        assert!(kept.borrow().is_empty());
+    }
+
+    #[test]
+    fn sources_that_cant_be_removed_are_kept() {
+        let folder = TempFolder::new("keep");
+        let source = folder.file("source.mkv", b"video content");
+        let target = folder.0.join("target.mkv");
+        let kept = RefCell::new(Vec::new());
+        copy_and_remove(
+            &source,
+            &target,
+            true,
+            |_| Err(io::Error::from(ErrorKind::PermissionDenied)),
+            |source| kept.borrow_mut().push(source.to_path_buf()),
+        )
+        .unwrap();
+        assert!(source.is_file());
+        assert!(target.is_file());
+        assert_eq!(*kept.borrow(), vec![source]);
+    }
+}
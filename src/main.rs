@@ -3,6 +3,9 @@ use sonarr::apis::api_info_api::api_get;
 
 pub mod bot;
 pub mod download_watcher;
+pub mod matcher;
+pub mod naming;
+pub mod parser;
 pub mod xml;
 
 #[allow(unreachable_code)]
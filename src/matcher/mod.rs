@@ -0,0 +1,230 @@
+use fancy_regex::Regex;
+use std::sync::OnceLock;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Leading articles that are ignored when comparing titles
+const ARTICLES: [&str; 6] = ["the", "a", "an", "der", "die", "das"];
+/// Roman numerals that number sequels like `Series Name II`
+const ROMAN_NUMERALS: [&str; 10] = ["i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix", "x"];
+/// Words that introduce the number of a season or part
+const SEASON_WORDS: [&str; 6] = ["season", "staffel", "part", "teil", "cour", "s"];
+
+static TRAILING_YEAR: OnceLock<Regex> = OnceLock::new();
+
+/// Will replace characters with diacritics by their base character, `ß` becomes `ss`
+pub fn fold_diacritics(title: &str) -> String {
+    title
+        .nfd()
+        .filter(|char| !is_combining_mark(*char))
+        .collect::<String>()
+        .replace('ß', "ss")
+}
+
+/// Will reduce a title to lowercase letters and digits so that punctuation and spacing don't matter
+pub fn clean_title(title: &str) -> String {
+    fold_diacritics(title)
+        .to_lowercase()
+        .chars()
+        .filter(|char| char.is_alphanumeric())
+        .collect()
+}
+
+/// Will split a trailing year like `Series Name (2023)` or `Series Name 2023` off the title
+pub fn split_year(title: &str) -> (String, Option<i32>) {
+    let pattern =
+        TRAILING_YEAR.get_or_init(|| Regex::new(r"^(.+?)[ .]*\(?((?:19|20)\d{2})\)?$").unwrap());
+    match pattern.captures(title.trim()) {
+        Ok(Some(captures)) => (
+            captures.get(1).unwrap().as_str().to_string(),
+            captures
+                .get(2)
+                .and_then(|year| year.as_str().parse::<i32>().ok()),
+        ),
+        _ => (title.trim().to_string(), None),
+    }
+}
+
+/// Checks if two titles are the same, ignoring punctuation, spacing and a trailing year
+///
+/// If both titles have a year, the years need to be the same
+pub fn is_same_title(
+    title: &str,
+    year: Option<i32>,
+    other_title: &str,
+    other_year: Option<i32>,
+) -> bool {
+    let (title, title_year) = split_year(title);
+    let (other_title, other_title_year) = split_year(other_title);
+    let year = year.or(title_year);
+    let other_year = other_year.or(other_title_year);
+    if year.is_some() && other_year.is_some() && year != other_year {
+        return false;
+    }
+    clean_title(&title) == clean_title(&other_title)
+}
+
+/// Will normalize a title for the similarity score
+///
+/// Diacritics, punctuation, a trailing year and a leading article are removed and the words are
+/// separated by single spaces
+pub fn normalize_for_matching(title: &str) -> String {
+    let (title, _) = split_year(title);
+    let title: String = fold_diacritics(&title)
+        .to_lowercase()
+        .chars()
+        .map(|char| if char.is_alphanumeric() { char } else { ' ' })
+        .collect();
+    let mut words: Vec<&str> = title.split_whitespace().collect();
+    if words.len() > 1 && ARTICLES.contains(&words[0]) {
+        words.remove(0);
+    }
+    words.join(" ")
+}
+
+/// Will collect the words of a normalized title that number a sequel or season
+///
+/// Digits, roman numerals, ordinals like `2nd` and words like `season` are kept in their order
+fn get_number_words(title: &str) -> Vec<String> {
+    normalize_for_matching(title)
+        .split_whitespace()
+        .filter(|word| {
+            word.chars().any(|char| char.is_ascii_digit())
+                || ROMAN_NUMERALS.contains(word)
+                || SEASON_WORDS.contains(word)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Checks if both titles have the same sequel and season numbers
+///
+/// Titles like `Series Name II` and `Series Name I` are very similar but never the same series
+pub fn has_same_numbers(title: &str, other_title: &str) -> bool {
+    get_number_words(title) == get_number_words(other_title)
+}
+
+/// Will score how similar two titles are, from 0 (nothing in common) to 1 (the same title)
+///
+/// If both titles have a year and the years differ, the score is 0
+pub fn similarity(title: &str, year: Option<i32>, other_title: &str) -> f64 {
+    let other_year = split_year(other_title).1;
+    let year = year.or(split_year(title).1);
+    if year.is_some() && other_year.is_some() && year != other_year {
+        return 0.0;
+    }
+    strsim::normalized_levenshtein(
+        &normalize_for_matching(title),
+        &normalize_for_matching(other_title),
+    )
+}
+
+/// Will score the title against all candidates, the best candidates come first
+pub fn rank<'a>(
+    title: &str,
+    year: Option<i32>,
+    candidates: impl Iterator<Item = &'a String>,
+) -> Vec<(String, f64)> {
+    let mut ranked: Vec<(String, f64)> = candidates
+        .map(|candidate| (candidate.clone(), similarity(title, year, candidate)))
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|(_, score), (_, other_score)| other_score.total_cmp(score));
+    ranked
+}
+
+/// Will rank the candidates for an autocomplete of the partial input, the best candidates come first
+///
+/// Candidates starting with the input come first, then candidates with a word starting with the
+/// input, then candidates containing the input and at last similar candidates
+pub fn rank_partial<'a>(
+    partial: &str,
+    candidates: impl Iterator<Item = &'a String>,
+) -> Vec<String> {
+    const MIN_SIMILARITY: f64 = 0.7;
+    let partial = fold_diacritics(partial.trim()).to_lowercase();
+    let mut ranked: Vec<(String, f64)> = candidates
+        .filter_map(|candidate| {
+            let folded = fold_diacritics(candidate).to_lowercase();
+            let score = if partial.is_empty() || folded.starts_with(&partial) {
+                4.0
+            } else if folded
+                .split(|char: char| !char.is_alphanumeric())
+                .any(|word| word.starts_with(&partial))
+            {
+                3.0
+            } else if folded.contains(&partial) {
+                2.0
+            } else {
+                strsim::jaro_winkler(&partial, &folded)
+            };
+            (score >= MIN_SIMILARITY).then(|| (candidate.clone(), score))
+        })
+        .collect();
+    ranked.sort_by(|(candidate, score), (other_candidate, other_score)| {
+        other_score
+            .total_cmp(score)
+            .then_with(|| candidate.cmp(other_candidate))
+    });
+    ranked.into_iter().map(|(candidate, _)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_for_matching_ignores_decorations() {
+        assert_eq!(normalize_for_matching("Series: Name (2023)"), "series name");
+        assert_eq!(normalize_for_matching("The Series Name"), "series name");
+        assert_eq!(normalize_for_matching("Schüler-Straße"), "schuler strasse");
+        assert_eq!(normalize_for_matching("The"), "the");
+    }
+
+    #[test]
+    fn similarity_respects_years() {
+        assert_eq!(similarity("series name", None, "series name (2023)"), 1.0);
+        assert_eq!(
+            similarity("series name", Some(2023), "series name (2023)"),
+            1.0
+        );
+        assert_eq!(
+            similarity("series name", Some(2019), "series name (2023)"),
+            0.0
+        );
+    }
+
+    #[test]
+    fn has_same_numbers_rejects_sequels() {
+        assert!(has_same_numbers("Series.Name.II", "series name ii"));
+        assert!(has_same_numbers("Series Name (2023)", "series name"));
+        assert!(!has_same_numbers("series name ii", "series name i"));
+        assert!(!has_same_numbers("series name 2", "series name 3"));
+        assert!(!has_same_numbers("series name season 2", "series name"));
+        assert!(!has_same_numbers("series name 2nd season", "series name"));
+    }
+
+    #[test]
+    fn rank_puts_best_candidate_first() {
+        let candidates = [
+            "other show".to_string(),
+            "series: name".to_string(),
+            "series names".to_string(),
+        ];
+        let ranked = rank("series name", None, candidates.iter());
+        assert_eq!(ranked[0].0, "series: name");
+        assert_eq!(ranked[1].0, "series names");
+    }
+
+    #[test]
+    fn rank_partial_prefers_prefixes() {
+        let candidates = [
+            "another series".to_string(),
+            "series name".to_string(),
+            "the series".to_string(),
+            "unrelated".to_string(),
+        ];
+        let ranked = rank_partial("Seri", candidates.iter());
+        assert_eq!(ranked, ["series name", "another series", "the series"]);
+    }
+}
@@ -0,0 +1,337 @@
+pub mod sonarr;
+
+use crate::matcher;
+use fancy_regex::{Captures, Regex};
+use log::warn;
+use std::sync::OnceLock;
+
+/// The default template of the season folder
+pub const DEFAULT_SEASON_FOLDER: &str = "Staffel {season:02}";
+/// The default template of the file name
+pub const DEFAULT_FILE: &str = "{series} - s{season:02}{episodes:02}.{ext}";
+
+/// The longest file name most filesystems allow, in bytes
+pub const MAX_FILE_NAME_LENGTH: usize = 255;
+
+static TOKEN: OnceLock<Regex> = OnceLock::new();
+static CLEAN_UP_RULES: OnceLock<Vec<(Regex, &str)>> = OnceLock::new();
+
+/// The templates used to name the videos in a library
+///
+/// Tokens are written like `{season}` or with zero padding like `{season:02}`
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamingTemplates {
+    /// The folder inside the series folder
+    pub season_folder: String,
+    /// The file name inside the season folder
+    pub file: String,
+    /// The folder inside the series folder for season 0
+    pub specials_folder: String,
+    /// The file name inside the specials folder
+    pub specials_file: String,
+}
+
+impl Default for NamingTemplates {
+    fn default() -> Self {
+        NamingTemplates {
+            season_folder: DEFAULT_SEASON_FOLDER.to_string(),
+            file: DEFAULT_FILE.to_string(),
+            specials_folder: DEFAULT_SEASON_FOLDER.to_string(),
+            specials_file: DEFAULT_FILE.to_string(),
+        }
+    }
+}
+
+/// Everything that can be used in a template
+#[derive(Clone, Debug, Default)]
+pub struct NamingContext {
+    /// The name of the series folder
+    pub series: String,
+    /// The title of the series if it is known
+    pub series_title: Option<String>,
+    /// The year of the series
+    pub year: Option<i32>,
+    /// The season in the library
+    pub season: i32,
+    /// All episodes contained in the file
+    pub episodes: Vec<i32>,
+    /// The absolute numbers of the episodes if they are known
+    pub absolute_episodes: Vec<i32>,
+    /// The title of the episode
+    pub episode_title: Option<String>,
+    /// The release group or fansub group
+    pub release_group: Option<String>,
+    /// The resolution like `1080p`
+    pub resolution: Option<String>,
+    /// The source like `WEB-DL`
+    pub source: Option<String>,
+    /// `Proper` or `REPACK` if the release replaces an earlier release
+    pub revision: Option<String>,
+    /// The file extension
+    pub extension: String,
+}
+
+impl NamingContext {
+    /// Will get the title of the series, or the name of the series folder without the year
+    pub fn series_title(&self) -> String {
+        match &self.series_title {
+            Some(series_title) => series_title.clone(),
+            None => matcher::split_year(&self.series).0,
+        }
+    }
+
+    /// Will get the quality like `WEB-DL-1080p`
+    pub fn quality(&self) -> String {
+        [&self.source, &self.resolution]
+            .into_iter()
+            .flatten()
+            .map(|value| sanitize(value))
+            .collect::<Vec<String>>()
+            .join("-")
+    }
+}
+
+impl NamingTemplates {
+    /// Will render the season folder, specials use their own template
+    pub fn season_folder(&self, context: &NamingContext) -> String {
+        match context.season {
+            0 => render(&self.specials_folder, context),
+            _ => render(&self.season_folder, context),
+        }
+    }
+
+    /// Will render the file name, specials use their own template
+    pub fn file_name(&self, context: &NamingContext) -> String {
+        match context.season {
+            0 => render(&self.specials_file, context),
+            _ => render(&self.file, context),
+        }
+    }
+
+    /// Will check if the file templates use tokens that need the episodes from Sonarr
+    pub fn needs_episode_details(&self) -> bool {
+        [&self.file, &self.specials_file]
+            .iter()
+            .any(|template| template.contains("{episode_title") || template.contains("{absolute"))
+    }
+}
+
+/// Will render the file name and shorten the episode title until the name fits the filesystem
+pub fn fit_file_name(
+    context: &mut NamingContext,
+    render: impl Fn(&NamingContext) -> String,
+) -> String {
+    loop {
+        let file_name = render(context);
+        let overflow = file_name.len().saturating_sub(MAX_FILE_NAME_LENGTH);
+        match &mut context.episode_title {
+            Some(episode_title) if overflow > 0 && !episode_title.is_empty() => {
+                let mut length = episode_title.len().saturating_sub(overflow);
+                while !episode_title.is_char_boundary(length) {
+                    length -= 1;
+                }
+                episode_title.truncate(length);
+                *episode_title = episode_title.trim_end().to_string();
+            }
+            _ => return file_name,
+        }
+    }
+}
+
+/// Will pad the number with zeros to the given width
+fn pad(number: i32, width: usize) -> String {
+    format!("{:0width$}", number, width = width)
+}
+
+/// Will make a value safe to use in a file name
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|char| match char {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => ' ',
+            _ => char,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Will get the value of a token, None if the token is unknown
+fn get_token_value(name: &str, width: usize, context: &NamingContext) -> Option<String> {
+    let first_episode = context.episodes.first().copied().unwrap_or_default();
+    let last_episode = context.episodes.last().copied().unwrap_or_default();
+    let optional = |value: &Option<String>| value.as_deref().map(sanitize).unwrap_or_default();
+    Some(match name {
+        "series" => sanitize(&context.series),
+        "series_title" => sanitize(&context.series_title()),
+        "year" => context
+            .year
+            .map(|year| year.to_string())
+            .unwrap_or_default(),
+        "season" => pad(context.season, width),
+        "episode" => pad(first_episode, width),
+        "last_episode" => pad(last_episode, width),
+        "absolute" => context
+            .absolute_episodes
+            .first()
+            .map(|absolute| pad(*absolute, width))
+            .unwrap_or_default(),
+        "episodes" => match context.episodes.len() {
+            0 | 1 => format!("e{}", pad(first_episode, width)),
+            _ => format!(
+                "e{}-e{}",
+                pad(first_episode, width),
+                pad(last_episode, width)
+            ),
+        },
+        "episode_title" => optional(&context.episode_title),
+        "release_group" => optional(&context.release_group),
+        "resolution" => optional(&context.resolution),
+        "source" => optional(&context.source),
+        "quality" => context.quality(),
+        "ext" => sanitize(&context.extension),
+        _ => return None,
+    })
+}
+
+/// Will remove what is left of empty tokens like `()`, `[]` and doubled or dangling separators
+fn clean_up(name: &str) -> String {
+    let rules = CLEAN_UP_RULES.get_or_init(|| {
+        [
+            (r"\(\s*\)|\[\s*]", ""),
+            (r"\s+", " "),
+            (r"(?: -)+ ", " - "),
+            (r" - (?=[\[(])", " "),
+            (r" (?:- ?)?\.(?=[^.]+$)", "."),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+        .collect()
+    });
+    let mut name = name.to_string();
+    for (pattern, replacement) in rules {
+        name = pattern.replace_all(&name, *replacement).to_string();
+    }
+    name.trim_matches([' ', '-']).to_string()
+}
+
+/// Will replace all tokens of the template with the values of the context
+///
+/// Unknown tokens are kept as they are
+pub fn render(template: &str, context: &NamingContext) -> String {
+    let token = TOKEN.get_or_init(|| Regex::new(r"\{([a-z_]+)(?::(\d+))?}").unwrap());
+    let rendered = token.replace_all(template, |captures: &Captures| {
+        let name = &captures[1];
+        let width = captures
+            .get(2)
+            .and_then(|width| width.as_str().parse::<usize>().ok())
+            .unwrap_or(1);
+        match get_token_value(name, width, context) {
+            Some(value) => value,
+            None => {
+                warn!("Unknown token {} in template {}", name, template);
+                captures[0].to_string()
+            }
+        }
+    });
+    clean_up(&rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> NamingContext {
+        NamingContext {
+            series: "Series Name".to_string(),
+            year: Some(2023),
+            season: 1,
+            episodes: vec![2],
+            release_group: Some("Group".to_string()),
+            resolution: Some("1080p".to_string()),
+            source: Some("WEB-DL".to_string()),
+            extension: "mkv".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_templates_keep_the_old_names() {
+        let templates = NamingTemplates::default();
+        let mut context = context();
+        assert_eq!(templates.season_folder(&context), "Staffel 01");
+        assert_eq!(templates.file_name(&context), "Series Name - s01e02.mkv");
+        context.episodes = vec![2, 3, 4];
+        assert_eq!(
+            templates.file_name(&context),
+            "Series Name - s01e02-e04.mkv"
+        );
+    }
+
+    #[test]
+    fn tokens_are_padded_and_cleaned_up() {
+        let mut context = context();
+        context.episodes = vec![7];
+        assert_eq!(
+            render(
+                "{series} ({year}) - S{season:02}E{episode:03} - {episode_title} [{quality}][{release_group}].{ext}",
+                &context
+            ),
+            "Series Name (2023) - S01E007 [WEB-DL-1080p][Group].mkv"
+        );
+        context.year = None;
+        context.release_group = None;
+        assert_eq!(
+            render(
+                "{series} ({year}) - {episode} [{release_group}].{ext}",
+                &context
+            ),
+            "Series Name - 7.mkv"
+        );
+    }
+
+    #[test]
+    fn specials_use_their_own_templates() {
+        let templates = NamingTemplates {
+            specials_folder: "Specials".to_string(),
+            specials_file: "{series} - Special {episode:02}.{ext}".to_string(),
+            ..Default::default()
+        };
+        let mut context = context();
+        context.season = 0;
+        assert_eq!(templates.season_folder(&context), "Specials");
+        assert_eq!(
+            templates.file_name(&context),
+            "Series Name - Special 02.mkv"
+        );
+    }
+
+    #[test]
+    fn long_episode_titles_are_truncated() {
+        let templates = NamingTemplates {
+            file: "{series} - s{season:02}{episodes:02} - {episode_title}.{ext}".to_string(),
+            ..Default::default()
+        };
+        let mut context = context();
+        context.episode_title = Some("Ü".repeat(200));
+        let file_name = fit_file_name(&mut context, |context| templates.file_name(context));
+        assert!(file_name.len() <= MAX_FILE_NAME_LENGTH);
+        assert!(file_name.starts_with("Series Name - s01e02 - ÜÜ"));
+        assert!(file_name.ends_with("Ü.mkv"));
+        context.episode_title = None;
+        assert_eq!(
+            fit_file_name(&mut context, |context| templates.file_name(context)),
+            "Series Name - s01e02.mkv"
+        );
+    }
+
+    #[test]
+    fn values_cannot_leave_the_folder() {
+        let mut context = context();
+        context.episode_title = Some("Part 1/2: The End".to_string());
+        assert_eq!(
+            render("{episode_title}.{ext}", &context),
+            "Part 1 2 The End.mkv"
+        );
+    }
+}
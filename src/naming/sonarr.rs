@@ -0,0 +1,356 @@
+use crate::naming::{pad, NamingContext};
+use fancy_regex::{Captures, Regex};
+use log::debug;
+use sonarr::models::NamingConfigResource;
+use std::sync::OnceLock;
+
+static EPISODE_TOKEN: OnceLock<Regex> = OnceLock::new();
+static TOKEN: OnceLock<Regex> = OnceLock::new();
+
+/// How Sonarr writes a file that contains multiple episodes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MultiEpisodeStyle {
+    /// `S01E01-02-03`
+    Extend,
+    /// `S01E01 - S01E02 - S01E03`
+    Duplicate,
+    /// `S01E01E02E03`
+    Repeat,
+    /// `S01E01-E02-E03`
+    Scene,
+    /// `S01E01-03`
+    Range,
+    /// `S01E01-E03`
+    PrefixedRange,
+}
+
+impl From<i32> for MultiEpisodeStyle {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => MultiEpisodeStyle::Duplicate,
+            2 => MultiEpisodeStyle::Repeat,
+            3 => MultiEpisodeStyle::Scene,
+            4 => MultiEpisodeStyle::Range,
+            5 => MultiEpisodeStyle::PrefixedRange,
+            _ => MultiEpisodeStyle::Extend,
+        }
+    }
+}
+
+/// The naming settings of Sonarr
+#[derive(Clone, Debug, PartialEq)]
+pub struct SonarrNaming {
+    /// If false Sonarr keeps the name of the downloaded file
+    pub rename_episodes: bool,
+    /// What a colon in a title is replaced with
+    pub colon_replacement: String,
+    /// How files with multiple episodes are named
+    pub multi_episode_style: MultiEpisodeStyle,
+    /// The file name of standard and daily series
+    pub standard_episode_format: String,
+    /// The file name of anime series
+    pub anime_episode_format: String,
+    /// The folder inside the series folder
+    pub season_folder_format: String,
+    /// The folder inside the series folder for season 0
+    pub specials_folder_format: String,
+}
+
+impl From<NamingConfigResource> for SonarrNaming {
+    fn from(config: NamingConfigResource) -> Self {
+        let format = |format: Option<Option<String>>, default: &str| {
+            format.flatten().unwrap_or(default.to_string())
+        };
+        SonarrNaming {
+            rename_episodes: config.rename_episodes.unwrap_or(false),
+            colon_replacement: match config.colon_replacement_format.unwrap_or(0) {
+                1 => "-".to_string(),
+                2 => " ".to_string(),
+                3 | 4 => " -".to_string(),
+                5 => config
+                    .custom_colon_replacement_format
+                    .flatten()
+                    .unwrap_or_default(),
+                _ => String::new(),
+            },
+            multi_episode_style: MultiEpisodeStyle::from(config.multi_episode_style.unwrap_or(0)),
+            standard_episode_format: format(
+                config.standard_episode_format,
+                "{Series Title} - S{season:00}E{episode:00} - {Episode Title}",
+            ),
+            anime_episode_format: format(
+                config.anime_episode_format,
+                "{Series Title} - S{season:00}E{episode:00} - {Episode Title}",
+            ),
+            season_folder_format: format(config.season_folder_format, "Season {season}"),
+            specials_folder_format: format(config.specials_folder_format, "Specials"),
+        }
+    }
+}
+
+impl SonarrNaming {
+    /// Will render the season folder like Sonarr does
+    pub fn season_folder(&self, context: &NamingContext) -> String {
+        match context.season {
+            0 => self.render(&self.specials_folder_format, context),
+            _ => self.render(&self.season_folder_format, context),
+        }
+    }
+
+    /// Will render the file name like Sonarr does
+    ///
+    /// Returns None if Sonarr doesn't rename episodes and the name of the download should be kept
+    pub fn file_name(&self, context: &NamingContext, anime: bool) -> Option<String> {
+        if !self.rename_episodes {
+            return None;
+        }
+        let format = if anime {
+            &self.anime_episode_format
+        } else {
+            &self.standard_episode_format
+        };
+        Some(format!(
+            "{}.{}",
+            self.render(format, context),
+            context.extension
+        ))
+    }
+
+    /// Will replace the episode tokens with all episodes in the multi episode style
+    fn render_episodes(&self, format: &str, context: &NamingContext) -> String {
+        let episode_token = EPISODE_TOKEN.get_or_init(|| {
+            Regex::new(r"(?i)(S\{season(?::0+)?}[^{}]*?)?([ex]?)\{(episode|absolute)(?::(0+))?}")
+                .unwrap()
+        });
+        episode_token
+            .replace_all(format, |captures: &Captures| {
+                let season_part = captures.get(1).map_or("", |part| part.as_str());
+                let prefix = &captures[2];
+                let width = captures.get(4).map_or(1, |zeros| zeros.as_str().len());
+                let numbers = match captures[3].to_lowercase().as_str() {
+                    "absolute" => &context.absolute_episodes,
+                    _ => &context.episodes,
+                };
+                let padded: Vec<String> =
+                    numbers.iter().map(|number| pad(*number, width)).collect();
+                let single = |number: &String| format!("{}{}", prefix, number);
+                let episodes = match (padded.as_slice(), self.multi_episode_style) {
+                    ([], _) => String::new(),
+                    ([number], _) => single(number),
+                    (_, MultiEpisodeStyle::Extend) => format!("{}{}", prefix, padded.join("-")),
+                    (_, MultiEpisodeStyle::Duplicate) => {
+                        return padded
+                            .iter()
+                            .map(|number| format!("{}{}", season_part, single(number)))
+                            .collect::<Vec<String>>()
+                            .join(" - ");
+                    }
+                    (_, MultiEpisodeStyle::Repeat) => padded.iter().map(single).collect(),
+                    (_, MultiEpisodeStyle::Scene) => {
+                        padded.iter().map(single).collect::<Vec<String>>().join("-")
+                    }
+                    ([first, .., last], MultiEpisodeStyle::Range) => {
+                        format!("{}-{}", single(first), last)
+                    }
+                    ([first, .., last], MultiEpisodeStyle::PrefixedRange) => {
+                        format!("{}-{}", single(first), single(last))
+                    }
+                };
+                format!("{}{}", season_part, episodes)
+            })
+            .to_string()
+    }
+
+    /// Will get the value of a Sonarr token, unsupported tokens are empty
+    fn get_token_value(&self, name: &str, width: usize, context: &NamingContext) -> String {
+        let series_title = context.series_title();
+        let value = match name.to_lowercase().as_str() {
+            "series title" => series_title,
+            "series titleyear" => match context.year {
+                None => series_title,
+                Some(year) => format!("{} ({})", series_title, year),
+            },
+            "series cleantitle" => clean(&series_title),
+            "series year" => context
+                .year
+                .map(|year| year.to_string())
+                .unwrap_or_default(),
+            "season" => pad(context.season, width),
+            "episode title" => context.episode_title.clone().unwrap_or_default(),
+            "episode cleantitle" => clean(&context.episode_title.clone().unwrap_or_default()),
+            "quality full" => [get_quality_title(context), context.revision.clone()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<String>>()
+                .join(" "),
+            "quality title" => get_quality_title(context).unwrap_or_default(),
+            "quality proper" => context.revision.clone().unwrap_or_default(),
+            "release group" => context.release_group.clone().unwrap_or_default(),
+            _ => {
+                debug!("Unsupported Sonarr token {}", name);
+                String::new()
+            }
+        };
+        value.replace(':', &self.colon_replacement)
+    }
+
+    /// Will render a Sonarr format
+    ///
+    /// Tokens can have a prefix and suffix like `{[Release Group]}` that is only written if the
+    /// token has a value, and use `.` or `_` instead of spaces like `{Series.Title}`
+    fn render(&self, format: &str, context: &NamingContext) -> String {
+        let token = TOKEN.get_or_init(|| {
+            Regex::new(
+                r"\{([^A-Za-z{}]*)([A-Za-z]+(?:[ ._][A-Za-z]+)*)(?::(0+|\d+))?([^A-Za-z{}]*)}",
+            )
+            .unwrap()
+        });
+        let format = self.render_episodes(format, context);
+        let rendered = token.replace_all(&format, |captures: &Captures| {
+            let name = &captures[2];
+            let separator = name.chars().find(|char| ['.', '_'].contains(char));
+            let width = captures.get(3).map_or(1, |zeros| zeros.as_str().len());
+            let mut value = self.get_token_value(&name.replace(['.', '_'], " "), width, context);
+            if value.is_empty() {
+                return String::new();
+            }
+            if let Some(separator) = separator {
+                value = value.replace(' ', separator.to_string().as_str());
+            }
+            if name.chars().all(|char| !char.is_uppercase()) {
+                value = value.to_lowercase();
+            } else if name.chars().all(|char| !char.is_lowercase()) {
+                value = value.to_uppercase();
+            }
+            format!("{}{}{}", &captures[1], value, &captures[4])
+        });
+        super::clean_up(&super::sanitize(&rendered))
+    }
+}
+
+/// Will get the name Sonarr gives the quality of the source and resolution like `WEBDL-1080p`
+///
+/// Sonarr falls back to its lowest quality of a source if the resolution is unknown
+fn get_quality_title(context: &NamingContext) -> Option<String> {
+    let resolution = match context.resolution.as_deref() {
+        Some("4k") => Some("2160p"),
+        resolution => resolution,
+    };
+    let quality = match (context.source.as_deref(), resolution) {
+        (Some("WEB-DL" | "WEB"), resolution) => format!("WEBDL-{}", resolution.unwrap_or("480p")),
+        (Some("WEBRip"), resolution) => format!("WEBRip-{}", resolution.unwrap_or("480p")),
+        (Some("BluRay" | "BDRip"), resolution) => {
+            format!("Bluray-{}", resolution.unwrap_or("720p"))
+        }
+        (Some("DVD" | "DVDRip"), _) => "DVD".to_string(),
+        (Some("HDTV") | None, Some("480p" | "576p")) | (Some("HDTV"), None) => "SDTV".to_string(),
+        (Some("HDTV") | None, Some(resolution)) => format!("HDTV-{}", resolution),
+        _ => return None,
+    };
+    Some(quality)
+}
+
+/// Will remove everything but letters, digits and spaces like the clean titles of Sonarr
+fn clean(title: &str) -> String {
+    title
+        .chars()
+        .filter(|char| char.is_alphanumeric() || *char == ' ')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naming(multi_episode_style: MultiEpisodeStyle) -> SonarrNaming {
+        SonarrNaming {
+            rename_episodes: true,
+            colon_replacement: " -".to_string(),
+            multi_episode_style,
+            standard_episode_format:
+                "{Series TitleYear} - S{season:00}E{episode:00} - {Episode Title} {[Quality Full]}"
+                    .to_string(),
+            anime_episode_format: "{Series.Title}.{absolute:000}{-Release Group}".to_string(),
+            season_folder_format: "Season {season}".to_string(),
+            specials_folder_format: "Specials".to_string(),
+        }
+    }
+
+    fn context() -> NamingContext {
+        NamingContext {
+            series: "Series Name (2023)".to_string(),
+            year: Some(2023),
+            season: 1,
+            episodes: vec![2, 3],
+            absolute_episodes: vec![14, 15],
+            episode_title: Some("Title: Part 1".to_string()),
+            release_group: Some("Group".to_string()),
+            resolution: Some("1080p".to_string()),
+            source: Some("WEB-DL".to_string()),
+            extension: "mkv".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_like_sonarr() {
+        let mut context = context();
+        assert_eq!(
+            naming(MultiEpisodeStyle::PrefixedRange).file_name(&context, false),
+            Some("Series Name (2023) - S01E02-E03 - Title - Part 1 [WEBDL-1080p].mkv".to_string())
+        );
+        assert_eq!(
+            naming(MultiEpisodeStyle::Duplicate).file_name(&context, false),
+            Some(
+                "Series Name (2023) - S01E02 - S01E03 - Title - Part 1 [WEBDL-1080p].mkv"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            naming(MultiEpisodeStyle::Range).file_name(&context, true),
+            Some("Series.Name.014-015-Group.mkv".to_string())
+        );
+        context.episode_title = None;
+        context.source = None;
+        context.resolution = None;
+        assert_eq!(
+            naming(MultiEpisodeStyle::Extend).file_name(&context, false),
+            Some("Series Name (2023) - S01E02-03.mkv".to_string())
+        );
+    }
+
+    #[test]
+    fn uses_the_quality_names_of_sonarr() {
+        let mut context = context();
+        let quality = |context: &NamingContext| {
+            naming(MultiEpisodeStyle::Extend).get_token_value("Quality Full", 0, context)
+        };
+        assert_eq!(quality(&context), "WEBDL-1080p");
+        context.revision = Some("REPACK".to_string());
+        assert_eq!(quality(&context), "WEBDL-1080p REPACK");
+        context.revision = None;
+        context.source = Some("HDTV".to_string());
+        context.resolution = Some("480p".to_string());
+        assert_eq!(quality(&context), "SDTV");
+        context.source = Some("BluRay".to_string());
+        context.resolution = Some("4k".to_string());
+        assert_eq!(quality(&context), "Bluray-2160p");
+        context.source = None;
+        context.resolution = None;
+        assert_eq!(quality(&context), "");
+    }
+
+    #[test]
+    fn renders_season_folders() {
+        let mut context = context();
+        assert_eq!(
+            naming(MultiEpisodeStyle::Extend).season_folder(&context),
+            "Season 1"
+        );
+        context.season = 0;
+        assert_eq!(
+            naming(MultiEpisodeStyle::Extend).season_folder(&context),
+            "Specials"
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use crate::xml;
+use fancy_regex::Regex;
+use log::{error, info, warn};
+use std::sync::{OnceLock, RwLock};
+
+/// The rule that is used when no valid rule is configured
+pub const DEFAULT_RULE: (&str, &str) = (
+    "default",
+    r"(?i)^(?:\[.*] *)?(?<title>.+?)(?:[ (.]+(?<year>20\d{2})[ ).-]+)?(?:s(?<season>\d+))?[- ]*(?<!-)e?(?<episode>\d+)(?:(?:-?e|-)(?<last_episode>\d+))*(?!-).*?(?:.*)?\.(?<ext>[a-zA-Z0-9]*)",
+);
+
+/// A named regex to parse file names
+///
+/// The regex needs the named groups `title`, `episode` and `ext` and can have the groups
+/// `season`, `last_episode`, `year`, `release_group`, `resolution`, `source` and `crc`
+struct ParseRule {
+    name: String,
+    pattern: Regex,
+}
+
+/// Everything that could be extracted from the file name of a release
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedRelease {
+    /// The name of the rule that matched
+    pub rule: String,
+    /// The normalized lowercase title
+    pub title: String,
+    /// The year of the series if it was in the file name
+    pub year: Option<i32>,
+    /// The season if there was one
+    pub season: Option<i32>,
+    /// All episodes contained in the file
+    pub episodes: Vec<i32>,
+    /// The release group or fansub group
+    pub release_group: Option<String>,
+    /// The resolution like `1080p`
+    pub resolution: Option<String>,
+    /// The source like `WEB-DL` or `BluRay`
+    pub source: Option<String>,
+    /// `Proper` or `REPACK` if the release replaces an earlier release
+    pub revision: Option<String>,
+    /// The CRC32 checksum fansub groups put in the file name
+    pub crc: Option<String>,
+    /// The air date of a daily episode like `2024-03-15`
+    pub air_date: Option<String>,
+    /// The file extension
+    pub extension: String,
+}
+
+/// Parses file names with an ordered list of rules where the first matching rule wins
+pub struct Parser {
+    rules: Vec<ParseRule>,
+}
+
+/// The regexes used to find the metadata that no rule extracted
+struct Detectors {
+    fansub_group: Regex,
+    scene_group: Regex,
+    resolution: Regex,
+    source: Regex,
+    revision: Regex,
+    crc: Regex,
+    air_date: Regex,
+}
+
+static DETECTORS: OnceLock<Detectors> = OnceLock::new();
+
+static PARSER: RwLock<Parser> = RwLock::new(Parser { rules: Vec::new() });
+
+/// Will compile the metadata regexes once
+fn get_detectors<'a>() -> &'a Detectors {
+    DETECTORS.get_or_init(|| Detectors {
+        fansub_group: Regex::new(r"^\[([^\]]+)]").unwrap(),
+        scene_group: Regex::new(r"-([a-zA-Z0-9]+)(?:\[[^\]]*])?\)?\.[a-zA-Z0-9]+$").unwrap(),
+        resolution: Regex::new(r"(?i)\b(\d{3,4}p|4k)\b").unwrap(),
+        source: Regex::new(
+            r"(?i)\b(web[ .-]?dl|web[ .-]?rip|web|blu[ .-]?ray|bd[ .-]?rip|hdtv|dvd[ .-]?rip|dvd)\b",
+        )
+        .unwrap(),
+        revision: Regex::new(r"(?i)\b(proper|repack)\b").unwrap(),
+        crc: Regex::new(r"\[([0-9a-fA-F]{8})]").unwrap(),
+        air_date: Regex::new(
+            r"(?<!\d)((?:19|20)\d{2})[ ._-](0[1-9]|1[0-2])[ ._-](0[1-9]|[12]\d|3[01])(?!\d)",
+        )
+        .unwrap(),
+    })
+}
+
+/// Will normalize a title so that the same title is always written the same way
+///
+/// Dots, dashes and commas become spaces and multiple spaces are collapsed into one
+pub fn normalize_title(title: &str) -> String {
+    title
+        .to_lowercase()
+        .replace(['.', '-', ','], " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Will bring the different spellings of a source to one name
+fn normalize_source(source: &str) -> String {
+    match source.to_lowercase().replace([' ', '.', '-'], "").as_str() {
+        "webdl" => "WEB-DL",
+        "webrip" => "WEBRip",
+        "web" => "WEB",
+        "bluray" => "BluRay",
+        "bdrip" => "BDRip",
+        "hdtv" => "HDTV",
+        "dvdrip" => "DVDRip",
+        "dvd" => "DVD",
+        other => return other.to_string(),
+    }
+    .to_string()
+}
+
+/// Will get the first group of the regex if it matches
+fn find_first_group(pattern: &Regex, text: &str) -> Option<String> {
+    match pattern.captures(text) {
+        Ok(Some(captures)) => captures.get(1).map(|group| group.as_str().to_string()),
+        _ => None,
+    }
+}
+
+impl Parser {
+    /// Creates a parser with the given rules, keeping their order
+    ///
+    /// The Entries are like this: (name, regex). Invalid rules are skipped, if no rule is left the
+    /// default rule is used
+    pub fn new(rules: Vec<(String, String)>) -> Parser {
+        let mut rules: Vec<ParseRule> = rules
+            .into_iter()
+            .filter_map(|(name, pattern)| match Regex::new(pattern.as_str()) {
+                Ok(pattern) => Some(ParseRule { name, pattern }),
+                Err(err) => {
+                    error!("Parse rule {name} is not a valid regex: {:?}", err);
+                    None
+                }
+            })
+            .collect();
+        if rules.is_empty() {
+            info!("No valid parse rules found, using the default rule");
+            rules.push(ParseRule {
+                name: DEFAULT_RULE.0.to_string(),
+                pattern: Regex::new(DEFAULT_RULE.1).unwrap(),
+            });
+        }
+        Parser { rules }
+    }
+
+    /// Will parse the file name with the first matching rule
+    pub fn parse(&self, file_name: &str) -> Option<ParsedRelease> {
+        let lowercase_name = file_name.to_lowercase();
+        for rule in self.rules.iter() {
+            let captures = match rule.pattern.captures(lowercase_name.as_str()) {
+                Ok(Some(captures)) => captures,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!(
+                        "Parse rule {} failed on {}: {:?}",
+                        rule.name, file_name, err
+                    );
+                    continue;
+                }
+            };
+            let group = |name: &str| captures.name(name).map(|group| group.as_str());
+            let number = |name: &str| group(name).and_then(|number| number.parse::<i32>().ok());
+            let (title, first_episode, extension) = match (
+                group("title"),
+                number("episode"),
+                group("ext"),
+            ) {
+                (Some(title), Some(episode), Some(extension)) => (title, episode, extension),
+                _ => {
+                    warn!(
+                        "Parse rule {} matched {} but is missing the title, episode or ext group",
+                        rule.name, file_name
+                    );
+                    continue;
+                }
+            };
+            let episodes = match number("last_episode") {
+                Some(last_episode) if last_episode > first_episode => {
+                    (first_episode..=last_episode).collect()
+                }
+                _ => vec![first_episode],
+            };
+            let detectors = get_detectors();
+            let release_group = group("release_group").map(str::to_string).or_else(|| {
+                find_first_group(&detectors.fansub_group, file_name).or_else(|| {
+                    // a multi-episode suffix like -E03 is not a release group
+                    find_first_group(&detectors.scene_group, file_name).filter(|group| {
+                        !group
+                            .trim_start_matches(['e', 'E'])
+                            .chars()
+                            .all(|char| char.is_ascii_digit())
+                    })
+                })
+            });
+            return Some(ParsedRelease {
+                rule: rule.name.clone(),
+                title: normalize_title(title),
+                year: number("year"),
+                season: number("season"),
+                episodes,
+                release_group,
+                resolution: group("resolution")
+                    .map(str::to_string)
+                    .or_else(|| find_first_group(&detectors.resolution, file_name))
+                    .map(|resolution| resolution.to_lowercase()),
+                source: group("source")
+                    .map(str::to_string)
+                    .or_else(|| find_first_group(&detectors.source, file_name))
+                    .map(|source| normalize_source(&source)),
+                revision: find_first_group(&detectors.revision, file_name).map(|revision| {
+                    if revision.eq_ignore_ascii_case("repack") {
+                        "REPACK".to_string()
+                    } else {
+                        "Proper".to_string()
+                    }
+                }),
+                crc: group("crc")
+                    .map(str::to_string)
+                    .or_else(|| find_first_group(&detectors.crc, file_name))
+                    .map(|crc| crc.to_uppercase()),
+                air_date: find_air_date(&detectors.air_date, file_name),
+                extension: extension.to_string(),
+            });
+        }
+        None
+    }
+}
+
+/// Will find a date like `2024.03.15` in the file name and write it like `2024-03-15`
+fn find_air_date(pattern: &Regex, file_name: &str) -> Option<String> {
+    match pattern.captures(file_name) {
+        Ok(Some(captures)) => Some(format!(
+            "{}-{}-{}",
+            &captures[1], &captures[2], &captures[3]
+        )),
+        _ => None,
+    }
+}
+
+/// Will load the parse rules from the config into the shared parser
+pub fn reload() {
+    let parser = Parser::new(xml::get_parse_rules());
+    info!("Loaded {} parse rules", parser.rules.len());
+    *PARSER.write().unwrap() = parser;
+}
+
+/// Will parse the file name with the shared parser
+pub fn parse(file_name: &str) -> Option<ParsedRelease> {
+    PARSER.read().unwrap().parse(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const NAMES_FILE: &str = "test-file-names.lst";
+    const GOLDEN_FILE: &str = "test-file-names.golden";
+
+    /// Writes the parsed release in the format of the golden file
+    fn to_golden_line(parsed: Option<ParsedRelease>) -> String {
+        let optional = |value: Option<String>| value.unwrap_or("-".to_string());
+        match parsed {
+            None => "no match".to_string(),
+            Some(parsed) => format!(
+                "title={} | year={} | season={} | episodes={:?} | group={} | resolution={} | source={} | crc={} | ext={}",
+                parsed.title,
+                optional(parsed.year.map(|year| year.to_string())),
+                optional(parsed.season.map(|season| season.to_string())),
+                parsed.episodes,
+                optional(parsed.release_group),
+                optional(parsed.resolution),
+                optional(parsed.source),
+                optional(parsed.crc),
+                parsed.extension
+            ),
+        }
+    }
+
+    /// Parses every name of the names file and compares it to the golden file
+    ///
+    /// Run with `UPDATE_GOLDEN=1` to rewrite the golden file after an intended change
+    #[test]
+    fn golden_file_names() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let names = std::fs::read_to_string(root.join(NAMES_FILE)).unwrap();
+        let parser = Parser::new(Vec::new());
+        let actual: String = names
+            .lines()
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| format!("{}\n  {}\n", name, to_golden_line(parser.parse(name))))
+            .collect();
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(root.join(GOLDEN_FILE), &actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(root.join(GOLDEN_FILE)).unwrap();
+        let mismatches: Vec<String> = expected
+            .split_inclusive('\n')
+            .collect::<Vec<&str>>()
+            .chunks(2)
+            .zip(
+                actual
+                    .split_inclusive('\n')
+                    .collect::<Vec<&str>>()
+                    .chunks(2),
+            )
+            .filter(|(expected, actual)| expected != actual)
+            .map(|(expected, actual)| {
+                format!(
+                    "expected:\n{}actual:\n{}",
+                    expected.concat(),
+                    actual.concat()
+                )
+            })
+            .collect();
+        assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+        assert_eq!(expected.lines().count(), actual.lines().count());
+    }
+
+    #[test]
+    fn normalize_title_collapses_separators() {
+        assert_eq!(normalize_title("Series - Name"), "series name");
+        assert_eq!(normalize_title("series.name, the"), "series name the");
+        assert_eq!(normalize_title(" series  name "), "series name");
+    }
+
+    #[test]
+    fn air_dates_are_detected() {
+        let detectors = get_detectors();
+        assert_eq!(
+            find_air_date(&detectors.air_date, "Daily Show 2024.03.15 Guest 720p.mkv"),
+            Some("2024-03-15".to_string())
+        );
+        assert_eq!(
+            find_air_date(&detectors.air_date, "Series Name - s01e02 - 1080p.mkv"),
+            None
+        );
+        assert_eq!(
+            find_air_date(&detectors.air_date, "Series 12024-03-15.mkv"),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_rules_fall_back_to_default() {
+        let parser = Parser::new(vec![("broken".to_string(), "(".to_string())]);
+        let parsed = parser.parse("Series Name S01E02.mkv").unwrap();
+        assert_eq!(parsed.rule, DEFAULT_RULE.0);
+        assert_eq!(parsed.season, Some(1));
+        assert_eq!(parsed.episodes, vec![2]);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let parser = Parser::new(vec![
+            (
+                "never".to_string(),
+                r"^nothing-(?<title>.+)-(?<episode>\d+)\.(?<ext>\w+)$".to_string(),
+            ),
+            (
+                "dotted".to_string(),
+                r"^(?<title>.+?)\.s(?<season>\d+)e(?<episode>\d+).*\.(?<ext>\w+)$".to_string(),
+            ),
+            (DEFAULT_RULE.0.to_string(), DEFAULT_RULE.1.to_string()),
+        ]);
+        let parsed = parser.parse("Series.Name.S03E04.1080p.mkv").unwrap();
+        assert_eq!(parsed.rule, "dotted");
+        assert_eq!(parsed.title, "series name");
+        assert_eq!(parsed.season, Some(3));
+    }
+}
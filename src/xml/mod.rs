@@ -1,9 +1,12 @@
+use crate::naming::NamingTemplates;
+use crate::parser;
 use log::{error, info, warn};
 use sonarr::apis::configuration::{ApiKey, Configuration};
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::str::FromStr;
 use std::sync::{Mutex, OnceLock};
@@ -17,6 +20,48 @@ const DUMMY_CONTENT: &str = r##"
   <SonarrHost><!--Put here your Sonarr Host URL with protocol--></SonarrHost>
   <SonarrApiToken><!--Put here your Sonarr API Token--></SonarrApiToken>
   <MainChannel><!--Put here the Channel ID of the Main Channel--></MainChannel>
+  <WatchMode>inotify<!--inotify or poll (use poll for network mounts)--></WatchMode>
+  <ImportMode>local<!--local moves the videos itself, sonarr hands them to the manual import of Sonarr--></ImportMode>
+  <SettleTime>60<!--Seconds the size of a file must stay the same before it is moved--></SettleTime>
+  <VerifyChecksum>false<!--Compare the content after copying a file to another filesystem--></VerifyChecksum>
+  <MatchThreshold>0.9<!--Similarity between 0 and 1 above which an unknown title is matched to a folder automatically--></MatchThreshold>
+  <DownloadFolders>
+    <!--Folders the videos are downloaded to, defaults to <root>/Download-->
+    <!--Example: <DownloadFolder>/mnt/downloads/complete</DownloadFolder>-->
+  </DownloadFolders>
+  <PathTranslations seedFromSonarr="false">
+    <!--Translates the paths Sonarr uses to the paths the Download Watcher sees, the longest prefix wins-->
+    <!--seedFromSonarr adds the remote path mappings of Sonarr, for a Download Watcher that sees the downloads like the download client-->
+    <!--Example: <PathTranslation sonarr="/tv" local="/mnt/server/Shared Video"/>-->
+  </PathTranslations>
+  <Libraries>
+    <!--path defaults to <root>/Shared Video/<name>, kind can be series, anime or other-->
+    <!--transferMode can be move, copy or hardlink (falls back to copy across filesystems)-->
+    <!--A Library can contain its own <Naming> with the templates that differ from the global ones-->
+    <Library name="Anime" kind="anime" transferMode="move"/>
+    <Library name="Serien" kind="series" transferMode="move"/>
+  </Libraries>
+  <ParseRules>
+    <!--Ordered list of regexes with the named groups title, episode, ext and optionally season, last_episode, year-->
+    <!--Example: <ParseRule name="dotted"><![CDATA[^(?<title>.+?)\.s(?<season>\d+)e(?<episode>\d+).*\.(?<ext>\w+)$]]></ParseRule>-->
+  </ParseRules>
+  <Naming mode="templates">
+    <!--Mode templates uses the templates below, mode sonarr uses the naming settings of Sonarr-->
+    <!--Tokens: {series} {series_title} {year} {season} {episode} {last_episode} {episodes} {absolute} {episode_title} {quality} {resolution} {source} {release_group} {ext}-->
+    <!--Numbers can be padded with zeros like {season:02}-->
+    <SeasonFolder>Staffel {season:02}</SeasonFolder>
+    <File>{series} - s{season:02}{episodes:02}.{ext}</File>
+    <SpecialsFolder>Staffel {season:02}</SpecialsFolder>
+    <SpecialsFile>{series} - s{season:02}{episodes:02}.{ext}</SpecialsFile>
+  </Naming>
+  <IgnoredTitles>
+    <!--Titles that are never moved and never prompted for, filled by the ignore button of the mapping prompt-->
+  </IgnoredTitles>
+  <PartialSuffixes>
+    <Suffix>.part</Suffix>
+    <Suffix>.!qB</Suffix>
+    <Suffix>.crdownload</Suffix>
+  </PartialSuffixes>
 </root>"##;
 
 const CONFIG_FILE_NAME: &str = "appdata/Config.xml";
@@ -26,11 +71,60 @@ const BOT_TOKEN_TAG: &str = "BotToken";
 const SONARR_HOST_TAG: &str = "SonarrHost";
 const SONARR_API_TOKEN_TAG: &str = "SonarrApiToken";
 const MAIN_CHANNEL_TAG: &str = "MainChannel";
+const WATCH_MODE_TAG: &str = "WatchMode";
+const IMPORT_MODE_TAG: &str = "ImportMode";
+const SETTLE_TIME_TAG: &str = "SettleTime";
+const VERIFY_CHECKSUM_TAG: &str = "VerifyChecksum";
+const MATCH_THRESHOLD_TAG: &str = "MatchThreshold";
+
+// Partial Suffixes
+const PARTIAL_SUFFIXES_TAG: &str = "PartialSuffixes";
+const PARTIAL_SUFFIX_SINGLE_TAG: &str = "Suffix";
+// Partial Suffixes
+
+// Libraries
+const LIBRARIES_TAG: &str = "Libraries";
+const LIBRARY_SINGLE_TAG: &str = "Library";
+const NAME_ATTRIBUTE_TAG: &str = "name";
+const PATH_ATTRIBUTE_TAG: &str = "path";
+const KIND_ATTRIBUTE_TAG: &str = "kind";
+const TRANSFER_MODE_ATTRIBUTE_TAG: &str = "transferMode";
+const DOWNLOAD_FOLDERS_TAG: &str = "DownloadFolders";
+const PATH_TRANSLATIONS_TAG: &str = "PathTranslations";
+const PATH_TRANSLATION_SINGLE_TAG: &str = "PathTranslation";
+const SONARR_ATTRIBUTE_TAG: &str = "sonarr";
+const SEED_FROM_SONARR_ATTRIBUTE_TAG: &str = "seedFromSonarr";
+const LOCAL_ATTRIBUTE_TAG: &str = "local";
+const DOWNLOAD_FOLDER_SINGLE_TAG: &str = "DownloadFolder";
+// Libraries
+
+// Naming
+const NAMING_TAG: &str = "Naming";
+const MODE_ATTRIBUTE_TAG: &str = "mode";
+const SEASON_FOLDER_TAG: &str = "SeasonFolder";
+const FILE_TAG: &str = "File";
+const SPECIALS_FOLDER_TAG: &str = "SpecialsFolder";
+const SPECIALS_FILE_TAG: &str = "SpecialsFile";
+// Naming
+
+// Ignored Titles
+const IGNORED_TITLES_TAG: &str = "IgnoredTitles";
+const IGNORED_TITLE_SINGLE_TAG: &str = "Title";
+// Ignored Titles
+
+// Parse Rules
+const PARSE_RULES_TAG: &str = "ParseRules";
+const PARSE_RULE_SINGLE_TAG: &str = "ParseRule";
+// Parse Rules
 
 // Mappings
 const MAPPINGS_TAG: &str = "Mappings";
 const MAPPING_SINGLE_TAG: &str = "Mapping";
 const ALTERNATIVE_ATTRIBUTE_TAG: &str = "alternative";
+const TYPE_ATTRIBUTE_TAG: &str = "type";
+const SEASON_ATTRIBUTE_TAG: &str = "season";
+const EPISODE_OFFSET_ATTRIBUTE_TAG: &str = "episodeOffset";
+const ABSOLUTE_ATTRIBUTE_TAG: &str = "absolute";
 // Mappings
 
 pub static SONARR_CONFIGURATION: OnceLock<Configuration> = OnceLock::new();
@@ -204,28 +298,498 @@ pub fn get_main_channel() -> u64 {
     }
 }
 
-/// Will get known Mappings if there are any
+/// How the download folder is watched for new files
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WatchMode {
+    /// React to filesystem notifications (inotify)
+    Notify,
+    /// Scan the folder periodically
+    Poll,
+}
+
+/// Will retrieve the Watch Mode, defaults to [`WatchMode::Notify`]
+pub fn get_watch_mode() -> WatchMode {
+    let document = get_document();
+    match document
+        .get_child(WATCH_MODE_TAG)
+        .and_then(|element| element.get_text())
+    {
+        None => {
+            info!("No Watch Mode found, using inotify");
+            WatchMode::Notify
+        }
+        Some(mode) => match mode.trim().to_lowercase().as_str() {
+            "inotify" => WatchMode::Notify,
+            "poll" => WatchMode::Poll,
+            _ => {
+                warn!("Unknown Watch Mode {mode}, using inotify");
+                WatchMode::Notify
+            }
+        },
+    }
+}
+
+/// Who puts the videos into the libraries
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportMode {
+    /// The videos are renamed and moved by the Download Watcher
+    Local,
+    /// The videos are handed to the manual import of Sonarr, series unknown to Sonarr are moved locally
+    Sonarr,
+}
+
+/// Will retrieve the Import Mode, defaults to [`ImportMode::Local`]
+pub fn get_import_mode() -> ImportMode {
+    let document = get_document();
+    match document
+        .get_child(IMPORT_MODE_TAG)
+        .and_then(|element| element.get_text())
+    {
+        None => ImportMode::Local,
+        Some(mode) => match mode.trim().to_lowercase().as_str() {
+            "local" => ImportMode::Local,
+            "sonarr" => ImportMode::Sonarr,
+            _ => {
+                warn!("Unknown Import Mode {mode}, importing locally");
+                ImportMode::Local
+            }
+        },
+    }
+}
+
+/// Will retrieve the seconds a file needs to stay unchanged before it is moved, defaults to 60
+pub fn get_settle_time() -> u64 {
+    const DEFAULT_SETTLE_TIME: u64 = 60;
+    let document = get_document();
+    match document
+        .get_child(SETTLE_TIME_TAG)
+        .and_then(|element| element.get_text())
+    {
+        None => {
+            info!("No Settle Time found, using {DEFAULT_SETTLE_TIME}s");
+            DEFAULT_SETTLE_TIME
+        }
+        Some(text) => match u64::from_str(text.trim()) {
+            Ok(value) => value,
+            Err(_) => {
+                warn!("Settle Time is not an u64, using {DEFAULT_SETTLE_TIME}s");
+                DEFAULT_SETTLE_TIME
+            }
+        },
+    }
+}
+
+/// Will retrieve the similarity above which a title is matched to a folder without asking, defaults to 0.9
+pub fn get_match_threshold() -> f64 {
+    const DEFAULT_MATCH_THRESHOLD: f64 = 0.9;
+    let document = get_document();
+    match document
+        .get_child(MATCH_THRESHOLD_TAG)
+        .and_then(|element| element.get_text())
+    {
+        None => {
+            info!("No Match Threshold found, using {DEFAULT_MATCH_THRESHOLD}");
+            DEFAULT_MATCH_THRESHOLD
+        }
+        Some(text) => match f64::from_str(text.trim()) {
+            Ok(value) if (0.0..=1.0).contains(&value) => value,
+            _ => {
+                warn!("Match Threshold is not between 0 and 1, using {DEFAULT_MATCH_THRESHOLD}");
+                DEFAULT_MATCH_THRESHOLD
+            }
+        },
+    }
+}
+
+/// Will retrieve if the content of copies should be verified, defaults to false
+pub fn get_verify_checksum() -> bool {
+    let document = get_document();
+    match document
+        .get_child(VERIFY_CHECKSUM_TAG)
+        .and_then(|element| element.get_text())
+    {
+        None => false,
+        Some(text) => match bool::from_str(text.trim()) {
+            Ok(value) => value,
+            Err(_) => {
+                warn!("Verify Checksum is not a bool, not verifying copies");
+                false
+            }
+        },
+    }
+}
+
+/// Will retrieve the file suffixes of unfinished downloads
 ///
-/// The Entries in the HashMap are like this: (alt -> OG)
-pub fn get_mappings() -> HashMap<String, String> {
+/// Defaults to `.part`, `.!qB` and `.crdownload` if none are configured
+pub fn get_partial_suffixes() -> Vec<String> {
+    let document = get_document();
+    match document.get_child(PARTIAL_SUFFIXES_TAG) {
+        None => {
+            info!("No Partial Suffixes known, using defaults");
+            vec![
+                ".part".to_string(),
+                ".!qB".to_string(),
+                ".crdownload".to_string(),
+            ]
+        }
+        Some(element) => {
+            let mut output = Vec::new();
+            element.children.iter().for_each(|child| {
+                if let XMLNode::Element(element) = child {
+                    if element.name == PARTIAL_SUFFIX_SINGLE_TAG {
+                        match element.get_text() {
+                            None => warn!("Got Suffix without Text"),
+                            Some(suffix) => output.push(suffix.trim().to_string()),
+                        }
+                    } else {
+                        warn!("Got unknown Tag: {}", element.name);
+                    }
+                }
+            });
+            output
+        }
+    }
+}
+
+/// Where the names of the season folders and files come from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NamingMode {
+    /// The templates of the config
+    Templates,
+    /// The naming settings of Sonarr
+    Sonarr,
+}
+
+/// Will retrieve the Naming Mode, defaults to [`NamingMode::Templates`]
+pub fn get_naming_mode() -> NamingMode {
+    let document = get_document();
+    match document
+        .get_child(NAMING_TAG)
+        .and_then(|element| element.attributes.get(MODE_ATTRIBUTE_TAG))
+    {
+        None => NamingMode::Templates,
+        Some(mode) => match mode.trim().to_lowercase().as_str() {
+            "templates" => NamingMode::Templates,
+            "sonarr" => NamingMode::Sonarr,
+            _ => {
+                warn!("Unknown Naming Mode {mode}, using the templates");
+                NamingMode::Templates
+            }
+        },
+    }
+}
+
+/// Will overwrite the templates with the ones found in the naming element
+fn read_naming_templates(naming: &Element, templates: &mut NamingTemplates) {
+    for (tag, template) in [
+        (SEASON_FOLDER_TAG, &mut templates.season_folder),
+        (FILE_TAG, &mut templates.file),
+        (SPECIALS_FOLDER_TAG, &mut templates.specials_folder),
+        (SPECIALS_FILE_TAG, &mut templates.specials_file),
+    ] {
+        match naming.get_child(tag).and_then(|element| element.get_text()) {
+            None => info!("No {tag} template found in {}", naming.name),
+            Some(text) => *template = text.trim().to_string(),
+        }
+    }
+}
+
+/// Will retrieve the templates to name the videos, missing templates use the default
+///
+/// The templates of the library with the given name replace the global ones
+pub fn get_naming_templates(library: Option<&str>) -> NamingTemplates {
+    let document = get_document();
+    let mut templates = NamingTemplates::default();
+    match document.get_child(NAMING_TAG) {
+        None => info!("No Naming found, using the default templates"),
+        Some(naming) => read_naming_templates(naming, &mut templates),
+    }
+    if let Some(naming) = library
+        .and_then(|library| get_library_element(&document, library))
+        .and_then(|library| library.get_child(NAMING_TAG))
+    {
+        read_naming_templates(naming, &mut templates);
+    }
+    templates
+}
+
+/// Will retrieve the titles that should never be moved or prompted for
+pub fn get_ignored_titles() -> Vec<String> {
     let _lock = MAPPING_LOCK.lock();
     let document = get_document();
-    match document.get_child(MAPPINGS_TAG) {
+    match document.get_child(IGNORED_TITLES_TAG) {
+        None => Vec::new(),
+        Some(element) => {
+            let mut output = Vec::new();
+            element.children.iter().for_each(|child| {
+                if let XMLNode::Element(element) = child {
+                    if element.name == IGNORED_TITLE_SINGLE_TAG {
+                        match element.get_text() {
+                            None => warn!("Got Ignored Title without Text"),
+                            Some(title) => output.push(title.trim().to_string()),
+                        }
+                    } else {
+                        warn!("Got unknown Tag: {}", element.name);
+                    }
+                }
+            });
+            output
+        }
+    }
+}
+
+/// How a video gets from the download folder into a library
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferMode {
+    /// The video is removed from the download folder
+    Move,
+    /// The video stays in the download folder and a copy is placed in the library
+    Copy,
+    /// The video stays in the download folder and is hardlinked into the library
+    Hardlink,
+}
+
+impl FromStr for TransferMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "move" => Ok(TransferMode::Move),
+            "copy" => Ok(TransferMode::Copy),
+            "hardlink" => Ok(TransferMode::Hardlink),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What kind of videos a library contains
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LibraryKind {
+    /// Series that are not anime
+    Series,
+    /// Anime, named with the anime format of Sonarr
+    Anime,
+    /// Everything else
+    Other,
+}
+
+impl FromStr for LibraryKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "series" => Ok(LibraryKind::Series),
+            "anime" => Ok(LibraryKind::Anime),
+            "other" => Ok(LibraryKind::Other),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for LibraryKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LibraryKind::Series => write!(f, "series"),
+            LibraryKind::Anime => write!(f, "anime"),
+            LibraryKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// A folder that contains one folder per series
+#[derive(Clone, Debug, PartialEq)]
+pub struct Library {
+    /// The name used in the config and the messages
+    pub name: String,
+    /// The folder of the library
+    pub path: PathBuf,
+    /// What kind of videos the library contains
+    pub kind: LibraryKind,
+    /// How videos get into the library
+    pub transfer_mode: TransferMode,
+}
+
+/// Will find the library element with the given name
+fn get_library_element<'a>(document: &'a Element, library: &str) -> Option<&'a Element> {
+    document.get_child(LIBRARIES_TAG).and_then(|libraries| {
+        libraries.children.iter().find_map(|child| match child {
+            XMLNode::Element(element)
+                if element.name == LIBRARY_SINGLE_TAG
+                    && element
+                        .attributes
+                        .get(NAME_ATTRIBUTE_TAG)
+                        .is_some_and(|name| name.eq_ignore_ascii_case(library)) =>
+            {
+                Some(element)
+            }
+            _ => None,
+        })
+    })
+}
+
+/// Will retrieve the configured libraries
+///
+/// Libraries without a path are looked for in `Shared Video` below the given root folder
+pub fn get_libraries(root_folder: &Path) -> Vec<Library> {
+    read_libraries(&get_document(), root_folder)
+}
+
+/// Will read the libraries of the document
+///
+/// A config without `<Libraries>` uses the `Anime` and `Serien` libraries the Download Watcher
+/// always had before libraries could be configured
+fn read_libraries(document: &Element, root_folder: &Path) -> Vec<Library> {
+    const SHARED_VIDEO_FOLDER_NAME: &str = "Shared Video";
+    let mut output = Vec::new();
+    let Some(libraries) = document.get_child(LIBRARIES_TAG) else {
+        info!("No Libraries found, using Anime and Serien in the root folder");
+        for (name, kind) in [
+            ("Anime", LibraryKind::Anime),
+            ("Serien", LibraryKind::Series),
+        ] {
+            output.push(Library {
+                name: name.to_string(),
+                path: root_folder.join(SHARED_VIDEO_FOLDER_NAME).join(name),
+                kind,
+                transfer_mode: TransferMode::Move,
+            });
+        }
+        return output;
+    };
+    libraries.children.iter().for_each(|child| {
+        if let XMLNode::Element(element) = child {
+            if element.name != LIBRARY_SINGLE_TAG {
+                warn!("Got unknown Tag: {}", element.name);
+                return;
+            }
+            let Some(name) = element.attributes.get(NAME_ATTRIBUTE_TAG) else {
+                warn!("Got Library without {NAME_ATTRIBUTE_TAG} Tag");
+                return;
+            };
+            let path = match element.attributes.get(PATH_ATTRIBUTE_TAG) {
+                None => root_folder.join(SHARED_VIDEO_FOLDER_NAME).join(name),
+                Some(path) => PathBuf::from(path.trim()),
+            };
+            let kind = match element.attributes.get(KIND_ATTRIBUTE_TAG) {
+                None => LibraryKind::Series,
+                Some(kind) => LibraryKind::from_str(kind).unwrap_or_else(|_| {
+                    warn!("Unknown Library Kind {kind} for {name}, using series");
+                    LibraryKind::Series
+                }),
+            };
+            let transfer_mode = match element.attributes.get(TRANSFER_MODE_ATTRIBUTE_TAG) {
+                None => TransferMode::Move,
+                Some(mode) => TransferMode::from_str(mode).unwrap_or_else(|_| {
+                    warn!("Unknown Transfer Mode {mode} for {name}, using move");
+                    TransferMode::Move
+                }),
+            };
+            output.push(Library {
+                name: name.to_string(),
+                path,
+                kind,
+                transfer_mode,
+            });
+        }
+    });
+    output
+}
+
+/// Will retrieve the configured download folders, defaults to `Download` below the root folder
+pub fn get_download_folders(root_folder: &Path) -> Vec<PathBuf> {
+    const DOWNLOAD_FOLDER_NAME: &str = "Download";
+    let document = get_document();
+    let mut output = Vec::new();
+    if let Some(element) = document.get_child(DOWNLOAD_FOLDERS_TAG) {
+        element.children.iter().for_each(|child| {
+            if let XMLNode::Element(element) = child {
+                if element.name == DOWNLOAD_FOLDER_SINGLE_TAG {
+                    match element.get_text() {
+                        None => warn!("Got Download Folder without Text"),
+                        Some(path) => output.push(PathBuf::from(path.trim())),
+                    }
+                } else {
+                    warn!("Got unknown Tag: {}", element.name);
+                }
+            }
+        });
+    }
+    if output.is_empty() {
+        info!("No Download Folders found, using the one in the root folder");
+        output.push(root_folder.join(DOWNLOAD_FOLDER_NAME));
+    }
+    output
+}
+
+/// A folder that Sonarr sees under another path than the Download Watcher
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathTranslation {
+    /// The path as Sonarr sees it
+    pub sonarr: String,
+    /// The path as the Download Watcher sees it
+    pub local: PathBuf,
+}
+
+/// Will retrieve the translations between the paths of Sonarr and the local paths
+pub fn get_path_translations() -> Vec<PathTranslation> {
+    let document = get_document();
+    let mut output = Vec::new();
+    if let Some(element) = document.get_child(PATH_TRANSLATIONS_TAG) {
+        element.children.iter().for_each(|child| {
+            if let XMLNode::Element(element) = child {
+                if element.name != PATH_TRANSLATION_SINGLE_TAG {
+                    warn!("Got unknown Tag: {}", element.name);
+                    return;
+                }
+                match (
+                    element.attributes.get(SONARR_ATTRIBUTE_TAG),
+                    element.attributes.get(LOCAL_ATTRIBUTE_TAG),
+                ) {
+                    (Some(sonarr), Some(local)) => output.push(PathTranslation {
+                        sonarr: sonarr.trim().to_string(),
+                        local: PathBuf::from(local.trim()),
+                    }),
+                    _ => warn!(
+                        "Got Path Translation without {SONARR_ATTRIBUTE_TAG} or {LOCAL_ATTRIBUTE_TAG} Tag"
+                    ),
+                }
+            }
+        });
+    }
+    output
+}
+
+/// Will check if the remote path mappings of Sonarr should be added to the Path Translations
+pub fn get_seed_path_translations() -> bool {
+    let document = get_document();
+    document
+        .get_child(PATH_TRANSLATIONS_TAG)
+        .and_then(|element| element.attributes.get(SEED_FROM_SONARR_ATTRIBUTE_TAG))
+        .is_some_and(|seed| seed.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Will get the configured parse rules in their order
+///
+/// The Entries in the Vec are like this: (name, regex)
+pub fn get_parse_rules() -> Vec<(String, String)> {
+    let document = get_document();
+    match document.get_child(PARSE_RULES_TAG) {
         None => {
-            info!("No Mappings known");
-            HashMap::new()
+            info!("No Parse Rules known");
+            Vec::new()
         }
         Some(element) => {
-            let mut output = HashMap::new();
+            let mut output = Vec::new();
             element.children.iter().for_each(|child| {
                 if let XMLNode::Element(element) = child {
-                    if element.name == MAPPING_SINGLE_TAG {
-                        match element.attributes.get(ALTERNATIVE_ATTRIBUTE_TAG) {
-                            None => warn!("Got Mapping without {ALTERNATIVE_ATTRIBUTE_TAG} Tag"),
-                            Some(attribute) => match element.get_text() {
-                                None => warn!("Got Mapping without Text"),
-                                Some(og_text) => {
-                                    output.insert(attribute.to_string(), og_text.to_string());
+                    if element.name == PARSE_RULE_SINGLE_TAG {
+                        match element.attributes.get(NAME_ATTRIBUTE_TAG) {
+                            None => warn!("Got Parse Rule without {NAME_ATTRIBUTE_TAG} Tag"),
+                            Some(name) => match element.get_text() {
+                                None => warn!("Got Parse Rule without Text"),
+                                Some(regex) => {
+                                    output.push((name.to_string(), regex.trim().to_string()));
                                 }
                             },
                         }
@@ -239,6 +803,319 @@ pub fn get_mappings() -> HashMap<String, String> {
     }
 }
 
+/// Will get known Mappings if there are any
+///
+/// The Entries in the HashMap are like this: (alt -> OG)
+pub fn get_mappings() -> HashMap<String, String> {
+    let _lock = MAPPING_LOCK.lock();
+    let document = get_document();
+    match document.get_child(MAPPINGS_TAG) {
+        None => {
+            info!("No Mappings known");
+            HashMap::new()
+        }
+        Some(element) => read_mappings(element),
+    }
+}
+
+/// Will read the exact Mappings of the Mappings element (alt -> OG)
+fn read_mappings(mappings: &Element) -> HashMap<String, String> {
+    let mut output = HashMap::new();
+    mappings.children.iter().for_each(|child| {
+        if let XMLNode::Element(element) = child {
+            if element.name == MAPPING_SINGLE_TAG {
+                if get_mapping_type(element) != Some(MappingType::Exact) {
+                    return;
+                }
+                match element.attributes.get(ALTERNATIVE_ATTRIBUTE_TAG) {
+                    None => warn!("Got Mapping without {ALTERNATIVE_ATTRIBUTE_TAG} Tag"),
+                    Some(attribute) => match element.get_text() {
+                        None => warn!("Got Mapping without Text"),
+                        Some(og_text) => {
+                            output.insert(attribute.to_string(), og_text.to_string());
+                        }
+                    },
+                }
+            } else {
+                warn!("Got unknown Tag: {}", element.name);
+            }
+        }
+    });
+    output
+}
+
+/// Will retrieve the numbering of all exact Mappings that translate the numbers of the file name
+///
+/// The Entries in the HashMap are like this: (alt -> numbering)
+pub fn get_numberings() -> HashMap<String, Numbering> {
+    let _lock = MAPPING_LOCK.lock();
+    let document = get_document();
+    match document.get_child(MAPPINGS_TAG) {
+        None => HashMap::new(),
+        Some(mappings) => read_numberings(mappings),
+    }
+}
+
+/// Will read the numbering of the exact Mappings that translate the numbers (alt -> numbering)
+fn read_numberings(mappings: &Element) -> HashMap<String, Numbering> {
+    mappings
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            XMLNode::Element(element)
+                if element.name == MAPPING_SINGLE_TAG
+                    && get_mapping_type(element) == Some(MappingType::Exact) =>
+            {
+                let alt = element.attributes.get(ALTERNATIVE_ATTRIBUTE_TAG)?;
+                let numbering = get_numbering(element);
+                (numbering != Numbering::default()).then(|| (alt.to_string(), numbering))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// How the alternative name of a Mapping is compared to the title
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MappingType {
+    /// The normalized title needs to be the alternative name
+    Exact,
+    /// The alternative name is a regex the normalized title needs to match
+    Regex,
+    /// The alternative name is a glob with `*` and `?` the normalized title needs to match
+    Glob,
+}
+
+impl FromStr for MappingType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "exact" => Ok(MappingType::Exact),
+            "regex" => Ok(MappingType::Regex),
+            "glob" => Ok(MappingType::Glob),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for MappingType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MappingType::Exact => write!(f, "exact"),
+            MappingType::Regex => write!(f, "regex"),
+            MappingType::Glob => write!(f, "glob"),
+        }
+    }
+}
+
+/// A Mapping whose alternative name is a regex or glob
+#[derive(Clone, Debug)]
+pub struct PatternMapping {
+    /// The regex or glob
+    pub pattern: String,
+    /// If the pattern is a regex or a glob
+    pub mapping_type: MappingType,
+    /// The series name on the server
+    pub og: String,
+    /// How the numbers of the file name are translated
+    pub numbering: Numbering,
+}
+
+/// How the season and episode numbers of a file name are translated for a Mapping
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Numbering {
+    /// The season that is used instead of the season in the file name
+    pub season: Option<i32>,
+    /// The number that is added to every episode
+    pub episode_offset: i32,
+    /// If the episodes are absolute episodes, the season in the file name is ignored
+    pub absolute: bool,
+}
+
+impl Numbering {
+    /// Will translate the season and episodes of a file name
+    ///
+    /// A season of None means the episodes are absolute episodes. Returns None if the episode
+    /// offset would make an episode smaller than 1
+    pub fn translate(
+        &self,
+        season: Option<i32>,
+        episodes: &[i32],
+    ) -> Option<(Option<i32>, Vec<i32>)> {
+        let episodes: Vec<i32> = episodes
+            .iter()
+            .map(|episode| episode + self.episode_offset)
+            .collect();
+        if episodes.iter().any(|episode| *episode < 1) {
+            return None;
+        }
+        if self.absolute {
+            Some((None, episodes))
+        } else {
+            Some((self.season.or(season), episodes))
+        }
+    }
+}
+
+impl Display for Numbering {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(season) = self.season {
+            parts.push(format!("season {}", season));
+        }
+        if self.episode_offset != 0 {
+            parts.push(format!("episode offset {:+}", self.episode_offset));
+        }
+        if self.absolute {
+            parts.push("absolute".to_string());
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Will read the numbering attributes of a Mapping, invalid values are ignored
+fn get_numbering(element: &Element) -> Numbering {
+    let number = |attribute_tag: &str| match element.attributes.get(attribute_tag) {
+        None => None,
+        Some(value) => match i32::from_str(value.trim()) {
+            Ok(number) => Some(number),
+            Err(_) => {
+                warn!("{attribute_tag} of a Mapping is not a number, ignoring it");
+                None
+            }
+        },
+    };
+    Numbering {
+        season: number(SEASON_ATTRIBUTE_TAG),
+        episode_offset: number(EPISODE_OFFSET_ATTRIBUTE_TAG).unwrap_or(0),
+        absolute: element
+            .attributes
+            .get(ABSOLUTE_ATTRIBUTE_TAG)
+            .is_some_and(|absolute| absolute.trim().eq_ignore_ascii_case("true")),
+    }
+}
+
+/// Will write the numbering attributes of a Mapping, default values are removed
+fn set_numbering(element: &mut Element, numbering: &Numbering) {
+    element.attributes.remove(SEASON_ATTRIBUTE_TAG);
+    element.attributes.remove(EPISODE_OFFSET_ATTRIBUTE_TAG);
+    element.attributes.remove(ABSOLUTE_ATTRIBUTE_TAG);
+    if let Some(season) = numbering.season {
+        element
+            .attributes
+            .insert(SEASON_ATTRIBUTE_TAG.to_string(), season.to_string());
+    }
+    if numbering.episode_offset != 0 {
+        element.attributes.insert(
+            EPISODE_OFFSET_ATTRIBUTE_TAG.to_string(),
+            numbering.episode_offset.to_string(),
+        );
+    }
+    if numbering.absolute {
+        element
+            .attributes
+            .insert(ABSOLUTE_ATTRIBUTE_TAG.to_string(), "true".to_string());
+    }
+}
+
+/// Will get the type of the Mapping, a Mapping without a type is exact
+///
+/// Returns None if the type is unknown
+fn get_mapping_type(element: &Element) -> Option<MappingType> {
+    match element.attributes.get(TYPE_ATTRIBUTE_TAG) {
+        None => Some(MappingType::Exact),
+        Some(mapping_type) => match MappingType::from_str(mapping_type) {
+            Ok(mapping_type) => Some(mapping_type),
+            Err(_) => {
+                warn!("Got Mapping with unknown {TYPE_ATTRIBUTE_TAG}: {mapping_type}");
+                None
+            }
+        },
+    }
+}
+
+/// Will retrieve all Mappings with a regex or glob, in the order of the Config.xml
+pub fn get_pattern_mappings() -> Vec<PatternMapping> {
+    let _lock = MAPPING_LOCK.lock();
+    let document = get_document();
+    match document.get_child(MAPPINGS_TAG) {
+        None => Vec::new(),
+        Some(mappings) => read_pattern_mappings(mappings),
+    }
+}
+
+/// Will read the Mappings with a regex or glob of the Mappings element, in their order
+fn read_pattern_mappings(mappings: &Element) -> Vec<PatternMapping> {
+    mappings
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            XMLNode::Element(element) if element.name == MAPPING_SINGLE_TAG => Some(element),
+            _ => None,
+        })
+        .filter_map(|element| {
+            let mapping_type = get_mapping_type(element)?;
+            if mapping_type == MappingType::Exact {
+                return None;
+            }
+            let (pattern, og) = match (
+                element.attributes.get(ALTERNATIVE_ATTRIBUTE_TAG),
+                element.get_text(),
+            ) {
+                (Some(pattern), Some(og)) => (pattern.to_string(), og.to_string()),
+                _ => {
+                    warn!("Got {mapping_type} Mapping without {ALTERNATIVE_ATTRIBUTE_TAG} or Text");
+                    return None;
+                }
+            };
+            Some(PatternMapping {
+                pattern,
+                mapping_type,
+                og,
+                numbering: get_numbering(element),
+            })
+        })
+        .collect()
+}
+
+/// Will add a Mapping with a regex or glob, an existing Mapping with the same type and pattern is
+/// replaced
+pub fn add_pattern_mapping(mapping: PatternMapping) {
+    let _lock = MAPPING_LOCK.lock();
+    let mut document = get_document();
+    let mapping_type = mapping.mapping_type;
+    insert_pattern_mapping(get_mappings_element(&mut document), mapping);
+    info!("Added a {} Mapping", mapping_type);
+    write_document(document);
+}
+
+/// Will get the Mappings element of the document, it is added if there is none
+fn get_mappings_element(document: &mut Element) -> &mut Element {
+    if document.get_child(MAPPINGS_TAG).is_none() {
+        document
+            .children
+            .push(XMLNode::Element(Element::new(MAPPINGS_TAG)));
+    }
+    document.get_mut_child(MAPPINGS_TAG).unwrap()
+}
+
+/// Will add the Mapping with a regex or glob, the Mapping with the same type and pattern is replaced
+fn insert_pattern_mapping(mappings: &mut Element, mapping: PatternMapping) {
+    delete_mapping(mappings, &mapping.pattern, mapping.mapping_type);
+    let mut element = Element::new(MAPPING_SINGLE_TAG);
+    element
+        .attributes
+        .insert(ALTERNATIVE_ATTRIBUTE_TAG.to_string(), mapping.pattern);
+    element.attributes.insert(
+        TYPE_ATTRIBUTE_TAG.to_string(),
+        mapping.mapping_type.to_string(),
+    );
+    set_numbering(&mut element, &mapping.numbering);
+    element.children = vec![Text(mapping.og)];
+    mappings.children.push(XMLNode::Element(element));
+}
+
 /// Will add a Mapping to the Mappings
 pub fn add_mappings<S, U>(old: S, og: U)
 where
@@ -247,30 +1124,337 @@ where
 {
     let _lock = MAPPING_LOCK.lock();
     let mut document = get_document();
-    let mappings: &mut Element = match document.get_mut_child(MAPPINGS_TAG) {
+    if insert_mapping(get_mappings_element(&mut document), old.into(), og.into()) {
+        info!("Updated a Mapping");
+    } else {
+        info!("Added a Mapping");
+    }
+    write_document(document);
+}
+
+/// Will add an exact Mapping, returns true if the Mapping existed and only its series was updated
+fn insert_mapping(mappings: &mut Element, alt: String, og: String) -> bool {
+    // an existing alternative name is updated instead of added a second time
+    if let Some(mapping) = find_mapping(mappings, &alt, MappingType::Exact) {
+        set_og(mapping, &og);
+        return true;
+    }
+    let mut mapping = Element::new(MAPPING_SINGLE_TAG);
+    mapping
+        .attributes
+        .insert(ALTERNATIVE_ATTRIBUTE_TAG.to_string(), alt);
+    mapping.children = vec![Text(og)];
+    mappings.children.push(XMLNode::Element(mapping));
+    false
+}
+
+/// Checks if the element is the Mapping with the alternative name and type
+///
+/// Exact Mappings are compared normalized because older mappings may not be normalized, patterns
+/// need to be the same
+fn is_mapping_of(element: &Element, alt: &str, mapping_type: MappingType) -> bool {
+    if element.name != MAPPING_SINGLE_TAG || get_mapping_type(element) != Some(mapping_type) {
+        return false;
+    }
+    match element.attributes.get(ALTERNATIVE_ATTRIBUTE_TAG) {
+        None => false,
+        Some(attribute) if mapping_type == MappingType::Exact => {
+            parser::normalize_title(attribute) == parser::normalize_title(alt)
+        }
+        Some(attribute) => attribute == alt,
+    }
+}
+
+/// Will find the Mapping with the alternative name and type
+fn find_mapping<'a>(
+    mappings: &'a mut Element,
+    alt: &str,
+    mapping_type: MappingType,
+) -> Option<&'a mut Element> {
+    mappings.children.iter_mut().find_map(|child| match child {
+        XMLNode::Element(element) if is_mapping_of(element, alt, mapping_type) => Some(element),
+        _ => None,
+    })
+}
+
+/// Will remove the Mapping with the alternative name and type, returns false if there is none
+fn delete_mapping(mappings: &mut Element, alt: &str, mapping_type: MappingType) -> bool {
+    let count = mappings.children.len();
+    mappings.children.retain(|child| match child {
+        XMLNode::Element(element) => !is_mapping_of(element, alt, mapping_type),
+        _ => true,
+    });
+    mappings.children.len() != count
+}
+
+/// Will set the series name on the server of the Mapping
+fn set_og(mapping: &mut Element, og: &str) {
+    mapping.children = vec![Text(og.to_string())];
+}
+
+/// Will set the alternative name of the Mapping
+fn set_alt(mapping: &mut Element, alt: &str) {
+    mapping
+        .attributes
+        .insert(ALTERNATIVE_ATTRIBUTE_TAG.to_string(), alt.to_string());
+}
+
+/// Will change the Mapping with the alternative name and type and save it, returns false if there
+/// is none
+fn change_mapping<F>(alt: &str, mapping_type: MappingType, change: F) -> bool
+where
+    F: FnOnce(&mut Element),
+{
+    let _lock = MAPPING_LOCK.lock();
+    let mut document = get_document();
+    let mapping = match document
+        .get_mut_child(MAPPINGS_TAG)
+        .and_then(|mappings| find_mapping(mappings, alt, mapping_type))
+    {
+        None => return false,
+        Some(mapping) => mapping,
+    };
+    change(mapping);
+    write_document(document);
+    true
+}
+
+/// Will remove the Mapping with the alternative name and type, returns false if there is none
+pub fn remove_mapping(alt: &str, mapping_type: MappingType) -> bool {
+    let _lock = MAPPING_LOCK.lock();
+    let mut document = get_document();
+    let removed = document
+        .get_mut_child(MAPPINGS_TAG)
+        .is_some_and(|mappings| delete_mapping(mappings, alt, mapping_type));
+    if removed {
+        info!("Removed a Mapping");
+        write_document(document);
+    }
+    removed
+}
+
+/// Will change the series of the Mapping with the alternative name and type, returns false if
+/// there is none
+pub fn update_mapping(alt: &str, mapping_type: MappingType, og: &str) -> bool {
+    let updated = change_mapping(alt, mapping_type, |mapping| set_og(mapping, og));
+    if updated {
+        info!("Updated a Mapping");
+    }
+    updated
+}
+
+/// Will change how the numbers of the file name are translated for the Mapping with the
+/// alternative name and type, returns false if there is none
+pub fn update_numbering(alt: &str, mapping_type: MappingType, numbering: &Numbering) -> bool {
+    let updated = change_mapping(alt, mapping_type, |mapping| {
+        set_numbering(mapping, numbering)
+    });
+    if updated {
+        info!("Updated the numbering of a Mapping");
+    }
+    updated
+}
+
+/// Will change the alternative name of the Mapping with the type, returns false if there is none
+pub fn rename_mapping(alt: &str, mapping_type: MappingType, new_alt: &str) -> bool {
+    let renamed = change_mapping(alt, mapping_type, |mapping| set_alt(mapping, new_alt));
+    if renamed {
+        info!("Renamed a Mapping");
+    }
+    renamed
+}
+
+/// Will add a title that should be ignored from now on
+pub fn add_ignored_title<S>(title: S)
+where
+    S: Into<String>,
+{
+    let _lock = MAPPING_LOCK.lock();
+    let mut document = get_document();
+    let ignored_titles: &mut Element = match document.get_mut_child(IGNORED_TITLES_TAG) {
         None => {
             document
                 .children
-                .push(XMLNode::Element(Element::new(MAPPINGS_TAG)));
+                .push(XMLNode::Element(Element::new(IGNORED_TITLES_TAG)));
             write_document(document);
             drop(_lock);
-            add_mappings(old, og);
+            add_ignored_title(title);
             return;
         }
         Some(element) => element,
     };
-    let text = Text(og.into());
+    let mut ignored_title = Element::new(IGNORED_TITLE_SINGLE_TAG);
+    ignored_title.children = vec![Text(title.into())];
+    ignored_titles
+        .children
+        .push(XMLNode::Element(ignored_title));
 
-    let mut mapping = Element::new(MAPPING_SINGLE_TAG);
-    mapping
-        .attributes
-        .insert(ALTERNATIVE_ATTRIBUTE_TAG.to_string(), old.into());
-    mapping.children = vec![text];
+    info!("Added an Ignored Title");
+    write_document(document);
+}
 
-    let mut children = mappings.children.to_vec();
-    children.push(XMLNode::Element(mapping));
-    mappings.children = children;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    info!("Added a Mapping");
-    write_document(document);
+    /// Will write the element and read it again like the Config.xml
+    fn round_trip(element: &Element) -> Element {
+        let mut content = Vec::new();
+        element.write(&mut content).unwrap();
+        Element::parse(content.as_slice()).unwrap()
+    }
+
+    fn glob(pattern: &str, og: &str) -> PatternMapping {
+        PatternMapping {
+            pattern: pattern.to_string(),
+            mapping_type: MappingType::Glob,
+            og: og.to_string(),
+            numbering: Numbering::default(),
+        }
+    }
+
+    #[test]
+    fn mappings_are_added_edited_renamed_and_deleted() {
+        let mut mappings = Element::new(MAPPINGS_TAG);
+        assert!(!insert_mapping(
+            &mut mappings,
+            "series name".into(),
+            "series".into()
+        ));
+        assert!(insert_mapping(
+            &mut mappings,
+            "Series.Name".into(),
+            "other".into()
+        ));
+        let mut mappings = round_trip(&mappings);
+        assert_eq!(
+            read_mappings(&mappings),
+            HashMap::from([("series name".to_string(), "other".to_string())])
+        );
+
+        let mapping = find_mapping(&mut mappings, "series name", MappingType::Exact).unwrap();
+        set_og(mapping, "series");
+        set_alt(mapping, "new name");
+        set_numbering(
+            mapping,
+            &Numbering {
+                season: Some(2),
+                episode_offset: -12,
+                absolute: false,
+            },
+        );
+        let mut mappings = round_trip(&mappings);
+        assert_eq!(
+            read_mappings(&mappings),
+            HashMap::from([("new name".to_string(), "series".to_string())])
+        );
+        assert_eq!(read_numberings(&mappings)["new name"].episode_offset, -12);
+
+        assert!(!delete_mapping(
+            &mut mappings,
+            "series name",
+            MappingType::Exact
+        ));
+        assert!(delete_mapping(
+            &mut mappings,
+            "new name",
+            MappingType::Exact
+        ));
+        assert!(read_mappings(&round_trip(&mappings)).is_empty());
+    }
+
+    #[test]
+    fn exact_mappings_and_patterns_are_kept_apart() {
+        let mut mappings = Element::new(MAPPINGS_TAG);
+        insert_pattern_mapping(&mut mappings, glob("series*", "pattern series"));
+        // an exact Mapping with the text of a pattern doesn't replace the pattern
+        assert!(!insert_mapping(
+            &mut mappings,
+            "series*".into(),
+            "exact series".into()
+        ));
+        insert_pattern_mapping(&mut mappings, glob("series*", "new pattern series"));
+        let mut mappings = round_trip(&mappings);
+        assert_eq!(read_mappings(&mappings)["series*"], "exact series");
+        let patterns = read_pattern_mappings(&mappings);
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].og, "new pattern series");
+
+        let mapping = find_mapping(&mut mappings, "series*", MappingType::Glob).unwrap();
+        set_alt(mapping, "series?");
+        assert!(find_mapping(&mut mappings, "series*", MappingType::Regex).is_none());
+        assert!(delete_mapping(&mut mappings, "series*", MappingType::Exact));
+        let mappings = round_trip(&mappings);
+        assert!(read_mappings(&mappings).is_empty());
+        assert_eq!(read_pattern_mappings(&mappings)[0].pattern, "series?");
+    }
+
+    #[test]
+    fn numbers_are_translated() {
+        let season = Numbering {
+            season: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(season.translate(Some(1), &[5]), Some((Some(2), vec![5])));
+        assert_eq!(season.translate(None, &[5]), Some((Some(2), vec![5])));
+
+        let absolute = Numbering {
+            episode_offset: 12,
+            absolute: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            absolute.translate(Some(2), &[1, 2]),
+            Some((None, vec![13, 14]))
+        );
+
+        let to_season = Numbering {
+            season: Some(2),
+            episode_offset: -12,
+            absolute: false,
+        };
+        assert_eq!(
+            to_season.translate(Some(1), &[13, 14]),
+            Some((Some(2), vec![1, 2]))
+        );
+    }
+
+    #[test]
+    fn episodes_below_one_are_rejected() {
+        let numbering = Numbering {
+            season: Some(2),
+            episode_offset: -12,
+            absolute: false,
+        };
+        assert_eq!(numbering.translate(Some(1), &[12]), None);
+        // a range that crosses the offset is rejected as a whole
+        assert_eq!(numbering.translate(Some(1), &[12, 13]), None);
+        assert_eq!(numbering.translate(Some(1), &[0]), None);
+    }
+
+    #[test]
+    fn configs_without_libraries_use_the_old_folders() {
+        let document = Element::parse(
+            r#"<root>
+              <BotToken>token</BotToken>
+              <MainChannel>1</MainChannel>
+              <Mappings>
+                <Mapping alternative="series name">series name</Mapping>
+              </Mappings>
+            </root>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let libraries = read_libraries(&document, Path::new("/server"));
+        assert_eq!(libraries.len(), 2);
+        assert_eq!(libraries[0].name, "Anime");
+        assert_eq!(libraries[0].path, Path::new("/server/Shared Video/Anime"));
+        assert_eq!(libraries[0].kind, LibraryKind::Anime);
+        assert_eq!(libraries[1].name, "Serien");
+        assert_eq!(libraries[1].path, Path::new("/server/Shared Video/Serien"));
+        assert_eq!(libraries[1].kind, LibraryKind::Series);
+        assert!(libraries
+            .iter()
+            .all(|library| library.transfer_mode == TransferMode::Move));
+    }
 }